@@ -0,0 +1,69 @@
+//! Plays random playouts the way the Monte Carlo player does and reports the
+//! time and the heap allocations per move.  Run with `cargo bench`.
+
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+use threes::{
+    game::{Error, Game, Move},
+    rules::Rules,
+    tile::Tile,
+};
+
+// Counts every allocation made through it.
+struct Counting;
+
+static ALLOCS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static COUNTING: Counting = Counting;
+
+const PLAYOUTS: u32 = 20000;
+
+fn main() {
+    let mut rng = Pcg32::seed_from_u64(1);
+    let start: Game = Game::seeded(Rules::standard(), Tile::EMPTY, 0, 7);
+
+    let (mut moves, mut allocs) = (0u64, 0u64);
+    let t = Instant::now();
+    for _ in 0..PLAYOUTS {
+        let mut g = start.clone();
+        g.rerand_with(&mut rng);
+        let before = ALLOCS.load(Ordering::Relaxed);
+        while g.can_move() {
+            match g.apply(Move::ALL[rng.gen_range(0..4)]) {
+                Err(Error::IllegalMove) => {}
+                _ => moves += 1,
+            }
+        }
+        allocs += ALLOCS.load(Ordering::Relaxed) - before;
+        black_box(&g);
+    }
+    let elapsed = t.elapsed();
+    println!(
+        "{} playouts, {} moves: {:.1} ns/move, {:.3} allocations/move",
+        PLAYOUTS,
+        moves,
+        elapsed.as_nanos() as f64 / moves as f64,
+        allocs as f64 / moves as f64
+    );
+}
@@ -1,27 +1,44 @@
 use crossterm::{
     cursor,
-    event::{read, Event, KeyCode, KeyEvent, KeyEventKind},
+    event::{poll, read, Event, KeyCode, KeyEvent, KeyEventKind},
     style::{self, Stylize},
     terminal::{Clear, ClearType},
     ExecutableCommand,
 };
-use threes::game::Game;
+use threes::{
+    adversary::WorstCase,
+    blitz::{Blitz, Clock},
+    daily::{self, Date, ResultCode, RULES_VERSION},
+    game::{Game, Move, NextHint},
+    merge::Merge,
+    puzzle::{Puzzle, PuzzleGame, Status},
+    race::Race,
+    replay::Replay,
+    rules::Rules,
+    tile::Tile,
+    versus::{Turn, Versus},
+};
 
-use std::io::{self, stdout, Write};
+use std::{
+    env, fs,
+    io::{self, stdout, Write},
+    time::{Duration, Instant},
+};
 
-fn printone(n: u32) -> io::Result<()> {
+// 1 and 2 are only coloured in classic Threes, where they are special.
+fn printone(n: u32, merge: Merge) -> io::Result<()> {
     match n {
         0 => {
             stdout().execute(style::PrintStyledContent(
                 " 0 ".with(style::Color::White).on(style::Color::Black),
             ))?;
         }
-        1 => {
+        1 if merge == Merge::Classic => {
             stdout().execute(style::PrintStyledContent(
                 " 1 ".with(style::Color::White).on(style::Color::Blue),
             ))?;
         }
-        2 => {
+        2 if merge == Merge::Classic => {
             stdout().execute(style::PrintStyledContent(
                 " 2 ".with(style::Color::White).on(style::Color::Red),
             ))?;
@@ -46,87 +63,443 @@ fn printone(n: u32) -> io::Result<()> {
     Ok(())
 }
 
-fn printboard(g: &Game) -> io::Result<()> {
-    stdout()
-        .execute(Clear(ClearType::All))?
-        .execute(cursor::MoveTo(1, 1))?;
+// Draws the next tile and the board with their left edge at column x.
+fn printgame(g: &Game, x: u16) -> io::Result<()> {
+    stdout().execute(cursor::MoveTo(x, 1))?;
+    let merge = g.rules().merge;
     write!(stdout(), "Next: ")?;
-    match g.next().as_slice() {
-        [n] => printone(*n)?,
-        [n @ ..] => {
+    match g.next() {
+        NextHint::Exact(n) => printone(n.value(), merge)?,
+        hint => {
             stdout().execute(style::PrintStyledContent(
-                format!("{:?}", n)
+                format!("{:#}", hint)
                     .with(style::Color::Black)
                     .on(style::Color::Grey),
             ))?;
-            ()
         }
     };
 
-    stdout().execute(cursor::MoveTo(1, 2))?;
-    write!(stdout(), "----------------")?;
+    let b = g.board();
+    let (w, h) = (b.width(), b.height() as u16);
+    stdout().execute(cursor::MoveTo(x, 2))?;
+    write!(stdout(), "{}", "-".repeat(4 * w))?;
 
-    let b = g.board().0;
-    for row in 0..4 {
-        stdout().execute(cursor::MoveTo(1, 3 + row))?;
-        for col in 0..4 {
-            let n = b[(row * 4 + col) as usize];
+    for row in 0..h {
+        stdout().execute(cursor::MoveTo(x, 3 + row))?;
+        for col in 0..w {
+            let n = b.get(row as usize, col).unwrap().value();
             if n < 1000 {
                 print!(" ");
             }
-            printone(n)?;
+            printone(n, merge)?;
         }
     }
-    stdout().execute(cursor::MoveTo(1, 10))?;
+    Ok(())
+}
+
+fn printboard(
+    g: &Game,
+    puzzle: Option<&PuzzleGame>,
+    remaining: Option<Duration>,
+) -> io::Result<()> {
+    stdout().execute(Clear(ClearType::All))?;
+    printgame(g, 1)?;
+    let h = g.board().height() as u16;
+    if let Some(p) = puzzle {
+        stdout().execute(cursor::MoveTo(1, 4 + h))?;
+        write!(
+            stdout(),
+            "Goal: {} ({} moves left)",
+            p.puzzle().objective,
+            p.moves_left()
+        )?;
+    }
+    if let Some(t) = remaining {
+        stdout().execute(cursor::MoveTo(1, 4 + h))?;
+        write!(stdout(), "Time: {:.1}s", t.as_secs_f64())?;
+    }
+    stdout().execute(cursor::MoveTo(1, 5 + h))?;
+    write!(stdout(), "Score: {}", g.score())?;
+    stdout().execute(cursor::MoveTo(1, 6 + h))?;
     write!(stdout(), "Arrows to move; 'b' to undo; Esc or 'q' to exit.")?;
-    stdout().execute(cursor::MoveTo(1, 11))?;
+    stdout().execute(cursor::MoveTo(1, 7 + h))?;
+    Ok(())
+}
+
+// Plays a two-player race on a split screen until both players are done or
+// someone quits.
+fn race(rules: Rules, boost: Tile, boostpos: usize, target: u32) -> io::Result<()> {
+    let target = Tile::new(target);
+    let mut race = Race::new(rules, boost, boostpos, rand::random(), 2, target);
+    let (w, h) = (
+        race.game(0).board().width(),
+        race.game(0).board().height() as u16,
+    );
+    crossterm::terminal::enable_raw_mode()?;
+    stdout().execute(crossterm::cursor::Hide)?;
+    loop {
+        stdout().execute(Clear(ClearType::All))?;
+        for p in 0..2 {
+            let x = 1 + p as u16 * (4 * w as u16 + 8);
+            printgame(race.game(p), x)?;
+            let pr = race.progress(p);
+            stdout().execute(cursor::MoveTo(x, 4 + h))?;
+            match pr.place {
+                Some(place) => write!(stdout(), "Finished #{}", place)?,
+                None if pr.out => write!(stdout(), "Out")?,
+                None => write!(stdout(), "Moves: {}", pr.moves)?,
+            }
+            stdout().execute(cursor::MoveTo(x, 5 + h))?;
+            write!(stdout(), "Score: {}", pr.score)?;
+        }
+        stdout().execute(cursor::MoveTo(1, 6 + h))?;
+        write!(
+            stdout(),
+            "First to {}.  Left: WASD; right: arrows; Esc or 'q' to exit.",
+            target
+        )?;
+        stdout().execute(cursor::MoveTo(1, 7 + h))?;
+        if race.is_over() {
+            break;
+        }
+        let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = read()?
+        else {
+            continue;
+        };
+        let (p, mv) = match code {
+            KeyCode::Char('w') => (0, Move::Up),
+            KeyCode::Char('a') => (0, Move::Left),
+            KeyCode::Char('s') => (0, Move::Down),
+            KeyCode::Char('d') => (0, Move::Right),
+            KeyCode::Up => (1, Move::Up),
+            KeyCode::Left => (1, Move::Left),
+            KeyCode::Down => (1, Move::Down),
+            KeyCode::Right => (1, Move::Right),
+            KeyCode::Esc | KeyCode::Char('q') => break,
+            _ => continue,
+        };
+        let _ = race.apply(p, mv);
+    }
+    stdout().execute(crossterm::style::ResetColor)?;
+    stdout().execute(crossterm::cursor::Show)?;
+    crossterm::terminal::disable_raw_mode()?;
+    println!("\nexited");
+    for (i, p) in race.standings().into_iter().enumerate() {
+        let side = if p == 0 { "left" } else { "right" };
+        println!(
+            "{}. {} player, score {}",
+            i + 1,
+            side,
+            race.progress(p).score
+        );
+    }
     Ok(())
 }
 
+// Plays a versus game: a person slides with the arrows, and the new tiles are
+// placed by a second person (Tab picks the cell, 'v' the bonus value, Enter
+// places) or, with ai, by an adversary.
+fn versus(g: Game, ai: bool) -> io::Result<()> {
+    let mut v = Versus::new(g);
+    let (w, h) = (v.game().board().width(), v.game().board().height() as u16);
+    let (mut cell, mut value) = (0, 0);
+    crossterm::terminal::enable_raw_mode()?;
+    stdout().execute(crossterm::cursor::Hide)?;
+    loop {
+        stdout().execute(Clear(ClearType::All))?;
+        printgame(v.game(), 1)?;
+        let spawning = v.turn() == Turn::Spawn;
+        if spawning {
+            cell %= v.open().len();
+            value %= v.candidates().values().len();
+            // Mark the open cells, showing the chosen tile in the chosen one.
+            for (i, &pos) in v.open().iter().enumerate() {
+                let (x, y) = (2 + 4 * (pos % w) as u16, 3 + (pos / w) as u16);
+                stdout().execute(cursor::MoveTo(x, y))?;
+                let mark = if i == cell {
+                    format!("{:^3}", v.candidates().values()[value])
+                } else {
+                    " + ".to_string()
+                };
+                stdout().execute(style::PrintStyledContent(
+                    mark.with(style::Color::Black).on(style::Color::Yellow),
+                ))?;
+            }
+        }
+        stdout().execute(cursor::MoveTo(1, 5 + h))?;
+        write!(
+            stdout(),
+            "Score: {}  Moves: {}",
+            v.game().score(),
+            v.moves()
+        )?;
+        stdout().execute(cursor::MoveTo(1, 6 + h))?;
+        if spawning {
+            write!(
+                stdout(),
+                "Spawner: Tab for the cell, 'v' for the value, Enter to place."
+            )?;
+        } else {
+            write!(stdout(), "Slider: arrows to move; Esc or 'q' to exit.")?;
+        }
+        stdout().execute(cursor::MoveTo(1, 7 + h))?;
+        if v.is_over() {
+            break;
+        }
+        if spawning && ai {
+            let _ = v.play_adversary(&mut WorstCase { depth: 1 });
+            continue;
+        }
+        let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = read()?
+        else {
+            continue;
+        };
+        let _ = match (code, spawning) {
+            (KeyCode::Esc | KeyCode::Char('q'), _) => break,
+            (KeyCode::Up, false) => v.slide(Move::Up),
+            (KeyCode::Down, false) => v.slide(Move::Down),
+            (KeyCode::Left, false) => v.slide(Move::Left),
+            (KeyCode::Right, false) => v.slide(Move::Right),
+            (KeyCode::Tab, true) => {
+                cell += 1;
+                Ok(())
+            }
+            (KeyCode::Char('v'), true) => {
+                value += 1;
+                Ok(())
+            }
+            (KeyCode::Enter, true) => v
+                .place(v.open()[cell], v.candidates().values()[value])
+                .map(|_| ()),
+            _ => Ok(()),
+        };
+    }
+    stdout().execute(crossterm::style::ResetColor)?;
+    stdout().execute(crossterm::cursor::Show)?;
+    crossterm::terminal::disable_raw_mode()?;
+    println!("\nexited");
+    println!("score {} after {} moves", v.game().score(), v.moves());
+    Ok(())
+}
+
+// Usage: terminal [--hard] [--2048 | --fibonacci] [--size WxH] [--daily]
+//                 [--puzzle <puzzle.json>] [--blitz SECS | --move-clock SECS]
+//                 [--race TARGET] [--versus human|ai] [--record <replay.json>]
+//
+// With --hard, an adversary places every new tile where it hurts most.  With
+// --2048 or --fibonacci, the game follows the 2048 or Fibonacci Threes rules
+// instead.  With --size, the board is W wide and H high (3 to 6 each) instead
+// of 4x4.  With --daily, today's daily challenge is played instead (the other
+// game options, --hard, --race and --versus included, are ignored and undo is
+// off), and its result code is printed on exit.  With --puzzle, the puzzle in
+// the given file is played instead, and whether it was solved is printed on
+// exit.  With --blitz, the game ends after SECS seconds; with --move-clock, a
+// random move is played whenever SECS seconds pass without one (neither
+// applies to puzzles, and both turn off undo and --hard).  With --race, two
+// players race on a split screen to make a TARGET tile, with the same cards in
+// the same order (the game options apply to both).  With --versus, a second
+// person (or the AI) places every new tile instead of the rng.  With --record,
+// the game is saved on exit for `threes-ai-mc analyze` or `threes-ai-mc
+// verify`; it cannot be combined with --hard, --race, --versus or a puzzle
+// with scripted tiles, none of which a replay would reproduce.
+fn parse_size(s: &str) -> Option<(usize, usize)> {
+    let (w, h) = s.split_once('x')?;
+    let (w, h) = (w.parse().ok()?, h.parse().ok()?);
+    let ok = (3..=6).contains(&w) && (3..=6).contains(&h);
+    ok.then_some((w, h))
+}
+
 fn main() -> io::Result<()> {
-    let mut g = Game::new(192, 12);
+    let mut args = env::args().skip(1);
+    let mut record = None;
+    let mut hard = false;
+    let mut today = None;
+    let mut puzzle = None;
+    let mut clock = None;
+    let mut target = None;
+    let mut opponent = None;
+    let mut rules = Rules::standard();
+    while let Some(arg) = args.next() {
+        let ok = match arg.as_str() {
+            "--hard" => {
+                hard = true;
+                true
+            }
+            "--daily" => {
+                today = Some(Date::today());
+                true
+            }
+            "--puzzle" => match args.next().map(fs::read_to_string).transpose()? {
+                Some(json) => {
+                    let p: Puzzle = serde_json::from_str(&json)?;
+                    p.validate()
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    puzzle = Some(PuzzleGame::new(p));
+                    true
+                }
+                None => false,
+            },
+            "--blitz" | "--move-clock" => match args
+                .next()
+                .and_then(|v| v.parse().ok())
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            {
+                Some(limit) => {
+                    clock = Some(match arg.as_str() {
+                        "--blitz" => Clock::Total(limit),
+                        _ => Clock::PerMove {
+                            limit,
+                            auto_move: true,
+                        },
+                    });
+                    true
+                }
+                None => false,
+            },
+            "--race" => {
+                target = args.next().and_then(|v| v.parse().ok());
+                target.is_some()
+            }
+            "--versus" => {
+                opponent = args.next().filter(|o| o == "human" || o == "ai");
+                opponent.is_some()
+            }
+            "--record" => {
+                record = args.next();
+                record.is_some()
+            }
+            "--2048" => {
+                rules = Rules {
+                    width: rules.width,
+                    height: rules.height,
+                    ..Rules::twenty48()
+                };
+                true
+            }
+            "--fibonacci" => {
+                rules = Rules {
+                    width: rules.width,
+                    height: rules.height,
+                    initial_tiles: rules.initial_tiles,
+                    ..Rules::fibonacci()
+                };
+                true
+            }
+            "--size" => match args.next().as_deref().and_then(parse_size) {
+                Some((w, h)) => {
+                    rules.width = w;
+                    rules.height = h;
+                    rules.initial_tiles = rules.initial_tiles.min(w * h / 2);
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        };
+        if !ok {
+            println!(
+                "usage: terminal [--hard] [--2048 | --fibonacci] [--size WxH] [--daily] [--puzzle <puzzle.json>] [--blitz SECS | --move-clock SECS] [--race TARGET] [--versus human|ai] [--record <replay.json>]"
+            );
+            return Ok(());
+        }
+    }
+    // Everyone plays the same daily challenge.
+    if today.is_some() {
+        hard = false;
+        target = None;
+        opponent = None;
+    }
+    // A replay reproduces neither placed tiles nor a second player's game.
+    let scripted = today.is_none()
+        && puzzle
+            .as_ref()
+            .is_some_and(|p| !p.puzzle().script.is_empty());
+    let clash = [
+        (hard, "--hard"),
+        (target.is_some(), "--race"),
+        (opponent.is_some(), "--versus"),
+        (scripted, "a scripted --puzzle"),
+    ]
+    .into_iter()
+    .find(|&(on, _)| on);
+    if let (Some(_), Some((_, opt))) = (&record, clash) {
+        println!("--record cannot be used with {}", opt);
+        return Ok(());
+    }
+    let boostpos = (rules.height - 1) * rules.width;
+    let boost = if rules.merge == Merge::Classic {
+        Tile::three_times_pow2(6)
+    } else {
+        Tile::EMPTY
+    };
+    if let Some(target) = target {
+        return race(rules, boost, boostpos, target);
+    }
+    if let Some(opponent) = opponent {
+        return versus(Game::with_rules(rules, boost, boostpos), opponent == "ai");
+    }
+    let mut g = match (today, &puzzle) {
+        (Some(d), _) => daily::challenge(d, RULES_VERSION),
+        (None, Some(p)) => p.game().clone(),
+        (None, None) => Game::with_rules(rules, boost, boostpos),
+    };
+    let mut blitz = clock
+        .filter(|_| puzzle.is_none())
+        .map(|c| Blitz::new(g.clone(), c, Instant::now()));
     let mut old_g = g.clone();
+    let mut replay = Replay::new(g.clone());
+    let mut old_len = 0;
     crossterm::terminal::enable_raw_mode()?;
     stdout().execute(crossterm::cursor::Hide)?;
 
     loop {
-        printboard(&g)?;
-        if !g.can_move() {
+        let remaining = blitz.as_ref().map(|b| b.remaining(Instant::now()));
+        printboard(&g, puzzle.as_ref(), remaining)?;
+        let solving = puzzle
+            .as_ref()
+            .is_none_or(|p| p.status() == Status::Playing);
+        let timed_out = blitz.as_ref().is_some_and(|b| b.is_over());
+        if !g.can_move() || !solving || timed_out {
             break;
         }
-        match read()? {
+        if let Some(b) = &mut blitz {
+            // Keep the clock running while waiting for a key.
+            if !poll(Duration::from_millis(100))? {
+                if b.tick(Instant::now()) {
+                    g = b.game().clone();
+                }
+                continue;
+            }
+        }
+        let mv = match read()? {
             Event::Key(KeyEvent {
                 code: KeyCode::Up,
                 kind: KeyEventKind::Press,
                 ..
-            }) => {
-                old_g = g.clone();
-                let _ = g.up();
-            }
+            }) => Move::Up,
             Event::Key(KeyEvent {
                 code: KeyCode::Down,
                 kind: KeyEventKind::Press,
                 ..
-            }) => {
-                old_g = g.clone();
-                let _ = g.down();
-            }
+            }) => Move::Down,
             Event::Key(KeyEvent {
                 code: KeyCode::Left,
                 kind: KeyEventKind::Press,
                 ..
-            }) => {
-                old_g = g.clone();
-                let _ = g.left();
-            }
+            }) => Move::Left,
             Event::Key(KeyEvent {
                 code: KeyCode::Right,
                 kind: KeyEventKind::Press,
                 ..
-            }) => {
-                old_g = g.clone();
-                let _ = g.right();
-            }
+            }) => Move::Right,
             Event::Key(KeyEvent {
                 code: KeyCode::Esc,
                 kind: KeyEventKind::Press,
@@ -143,15 +516,65 @@ fn main() -> io::Result<()> {
                 code: KeyCode::Char('b'),
                 kind: KeyEventKind::Press,
                 ..
-            }) => {
+            }) if today.is_none() && puzzle.is_none() && blitz.is_none() => {
                 g = old_g.clone();
+                replay.truncate(old_len);
+                continue;
             }
-            _ => {}
+            _ => continue,
+        };
+        old_g = g.clone();
+        old_len = replay.moves().len();
+        let moved = if let Some(p) = &mut puzzle {
+            let res = p.apply(mv);
+            g = p.game().clone();
+            res.is_ok()
+        } else if let Some(b) = &mut blitz {
+            let res = b.apply(mv, Instant::now());
+            g = b.game().clone();
+            res.is_ok()
+        } else if hard {
+            g.apply_with(mv, &mut WorstCase { depth: 1 }).is_ok()
+        } else {
+            g.apply(mv).is_ok()
+        };
+        if moved {
+            replay.push(mv);
         }
     }
     stdout().execute(crossterm::style::ResetColor)?;
     stdout().execute(crossterm::cursor::Show)?;
     crossterm::terminal::disable_raw_mode()?;
     println!("\nexited");
+    match puzzle.map(|p| p.status()) {
+        Some(Status::Solved) => println!("puzzle solved"),
+        Some(Status::Failed) => println!("puzzle failed"),
+        Some(Status::Playing) => println!("puzzle abandoned"),
+        None => {}
+    }
+    if let Some(b) = &blitz {
+        replay = b.replay().clone();
+        match b.result() {
+            Some(r) => println!(
+                "blitz: score {} in {:.1}s, {} moves ({} played on timeouts){}",
+                r.score,
+                r.elapsed.as_secs_f64(),
+                r.moves,
+                r.auto_moves,
+                if r.timed_out { ", out of time" } else { "" }
+            ),
+            None => println!(
+                "blitz abandoned after {:.1}s",
+                b.elapsed(Instant::now()).as_secs_f64()
+            ),
+        }
+    }
+    if let Some(code) = today.and_then(|d| ResultCode::new(d, RULES_VERSION, &replay)) {
+        println!("daily result: {}", code);
+    }
+    if let Some(path) = record {
+        fs::write(&path, serde_json::to_string(&replay)?)?;
+        println!("replay saved to {}", path);
+    }
     Ok(())
 }
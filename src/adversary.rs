@@ -0,0 +1,190 @@
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+
+use crate::{
+    game::{Game, Move},
+    merge::MergeRule,
+    tile::Tile,
+};
+
+/// Decides where each new tile lands instead of the rng; see
+/// Game::apply_with.  R is the rng of the games it plays.
+pub trait Adversary<R = Pcg32> {
+    /// Picks one of the open positions and one of the candidate values.  g is
+    /// the game after the board has moved but before the tile is placed.
+    fn choose(&mut self, g: &Game<R>, open: &[usize], candidates: &[Tile]) -> (usize, Tile);
+}
+
+/// Places tiles uniformly at random, like a normal game.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Random;
+
+#[cfg(feature = "std")]
+impl<R> Adversary<R> for Random {
+    fn choose(&mut self, _: &Game<R>, open: &[usize], candidates: &[Tile]) -> (usize, Tile) {
+        let mut rng = thread_rng();
+        (
+            *open.choose(&mut rng).unwrap(),
+            *candidates.choose(&mut rng).unwrap(),
+        )
+    }
+}
+
+/// Places tiles to minimize the player's outcome, looking depth player moves
+/// ahead.  With depth 0 it just picks the spawn that leaves the fewest empty
+/// cells and merges.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct WorstCase {
+    pub depth: u32,
+}
+
+impl<R: RngCore + SeedableRng + Clone> Adversary<R> for WorstCase {
+    fn choose(&mut self, g: &Game<R>, open: &[usize], candidates: &[Tile]) -> (usize, Tile) {
+        let mut best = (open[0], candidates[0]);
+        let mut best_v = f32::MAX;
+        for &pos in open {
+            for &c in candidates {
+                let v = after_spawn(g, pos, c, self.depth);
+                if v < best_v {
+                    best = (pos, c);
+                    best_v = v;
+                }
+            }
+        }
+        best
+    }
+}
+
+// The value of a lost game; lower than any heuristic value.
+const LOSS: f32 = -1000.0;
+
+/// Returns the move that does best against a WorstCase adversary, searching
+/// depth moves ahead (minimax over player moves and adversary spawns), or None
+/// if no move is legal.  The previews drawn during the search come from a
+/// reshuffled copy of the game, so the search does not peek at the real
+/// upcoming tiles.
+#[cfg(feature = "std")]
+pub fn best_move<R: RngCore + SeedableRng + Clone>(g: &Game<R>, depth: u32) -> Option<Move> {
+    let mut g = g.clone();
+    g.rerand();
+    let mut best = None;
+    let mut best_v = f32::MIN;
+    for mv in Move::ALL {
+        if let Some(v) = after_move(&g, mv, depth.max(1)) {
+            if v > best_v {
+                best = Some(mv);
+                best_v = v;
+            }
+        }
+    }
+    best
+}
+
+// The value of g with the player to move.
+fn player<R: RngCore + SeedableRng + Clone>(g: &Game<R>, depth: u32) -> f32 {
+    if depth == 0 {
+        return heuristic(g);
+    }
+    Move::ALL
+        .iter()
+        .filter_map(|&mv| after_move(g, mv, depth))
+        .fold(LOSS, f32::max)
+}
+
+// The value of playing mv in g against the worst spawn, or None if illegal.
+fn after_move<R: RngCore + SeedableRng + Clone>(g: &Game<R>, mv: Move, depth: u32) -> Option<f32> {
+    let mut g = g.clone();
+    let open = g.slide(mv);
+    if open.is_empty() {
+        return None;
+    }
+    let mut worst = f32::MAX;
+    for &pos in &open {
+        for &c in g.next().values() {
+            worst = worst.min(after_spawn(&g, pos, c, depth - 1));
+        }
+    }
+    Some(worst)
+}
+
+// The value of placing c at pos in the (already shifted) game g.
+fn after_spawn<R: RngCore + SeedableRng + Clone>(
+    g: &Game<R>,
+    pos: usize,
+    c: Tile,
+    depth: u32,
+) -> f32 {
+    let mut g = g.clone();
+    match g.spawn(pos, c) {
+        Err(_) => LOSS,
+        Ok(_) => player(&g, depth),
+    }
+}
+
+// Counts empty cells and adjacent pairs that could merge.
+fn heuristic<R: RngCore + SeedableRng>(g: &Game<R>) -> f32 {
+    let b = g.board();
+    let rule = &g.rules().merge;
+    let combines = |a, b| rule.can_merge(a, b) || rule.can_merge(b, a);
+    let (c, w) = (b.cells(), b.width());
+    let mut n = b.empty_cells().count();
+    for i in 0..c.len() {
+        if i % w + 1 < w && combines(c[i], c[i + 1]) {
+            n += 1;
+        }
+        if i + w < c.len() && combines(c[i], c[i + w]) {
+            n += 1;
+        }
+    }
+    n as f32
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use crate::adversary::best_move;
+    use crate::adversary::{Adversary, WorstCase};
+    use crate::board::Board;
+    use crate::game::{Error, Game, Move};
+    use crate::tile::Tile;
+
+    // Always picks the last open position and the largest candidate.
+    struct Last;
+
+    impl Adversary for Last {
+        fn choose(&mut self, _: &Game, open: &[usize], candidates: &[Tile]) -> (usize, Tile) {
+            (*open.last().unwrap(), *candidates.iter().max().unwrap())
+        }
+    }
+
+    #[test]
+    fn apply_with() {
+        let b = Board::new([3, 0, 1, 2, 6, 6, 1, 2, 12, 6, 3, 1, 0, 3, 6, 6]);
+        let mut g = Game::with_board(b, &[6, 12]);
+        assert!(g.apply_with(Move::Left, &mut Last).is_ok());
+        assert_eq!(g.board().cells()[15], 12);
+        assert_eq!(g.board().cells()[3], 0);
+        assert_eq!(g.board().cells()[7], 0);
+    }
+
+    #[test]
+    fn worst_case() {
+        // Left vacates 3 and 15; a 6 merges at 15 but not at 3.
+        let b = Board::new([1, 2, 12, 24, 6, 24, 6, 12, 12, 6, 12, 6, 1, 2, 48, 24]);
+        let mut g = Game::with_board(b, &[6]);
+        assert!(g
+            .apply_with(Move::Left, &mut WorstCase { depth: 0 })
+            .is_ok());
+        assert_eq!(g.board().cells()[3], 6);
+        assert_eq!(g.board().cells()[15], 0);
+
+        // Now Left only vacates 3 and loses; Right is the only safe move.
+        let b = Board::new([1, 2, 12, 24, 6, 24, 6, 12, 12, 6, 12, 6, 3, 48, 24, 12]);
+        let mut g = Game::with_board(b, &[6]);
+        #[cfg(feature = "std")]
+        assert_eq!(best_move(&g, 1), Some(Move::Right));
+        let res = g.apply_with(Move::Left, &mut WorstCase { depth: 2 });
+        assert_eq!(res, Err(Error::GameOver));
+    }
+}
@@ -0,0 +1,167 @@
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::{
+    game::{Game, Move},
+    replay::Replay,
+};
+
+/// A search strategy used to judge moves.  Higher values are better; the
+/// scale is up to the strategy but must be consistent within one analysis.
+pub trait Evaluator {
+    /// Returns the value of playing mv in g, or None if mv is illegal.
+    fn evaluate(&mut self, g: &Game, mv: Move) -> Option<f32>;
+}
+
+/// The verdict on a single move of a replay.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct MoveAnalysis {
+    pub turn: usize,
+    pub played: Move,
+    pub best: Move,
+    /// Values in Move::ALL order; None for illegal moves.
+    pub values: [Option<f32>; 4],
+    /// How far (as a fraction of the best value) the played move fell short.
+    pub loss: f32,
+    pub blunder: bool,
+}
+
+impl MoveAnalysis {
+    pub fn value(&self, mv: Move) -> Option<f32> {
+        self.values[Move::ALL.iter().position(|&m| m == mv).unwrap()]
+    }
+}
+
+/// The result of analyzing a whole replay.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Report {
+    pub threshold: f32,
+    pub moves: Vec<MoveAnalysis>,
+}
+
+impl Report {
+    pub fn blunders(&self) -> impl Iterator<Item = &MoveAnalysis> {
+        self.moves.iter().filter(|m| m.blunder)
+    }
+}
+
+impl core::fmt::Display for Report {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for m in &self.moves {
+            write!(f, "{:4}: {:?}", m.turn, m.played)?;
+            if m.played != m.best {
+                write!(f, " (best {:?}, -{:.0}%)", m.best, m.loss * 100.0)?;
+            }
+            if m.blunder {
+                write!(f, " BLUNDER")?;
+            }
+            write!(f, "   [")?;
+            for (mv, v) in Move::ALL.iter().zip(m.values) {
+                match v {
+                    Some(v) => write!(f, " {:?}={:.1}", mv, v)?,
+                    None => write!(f, " {:?}=-", mv)?,
+                }
+            }
+            writeln!(f, " ]")?;
+        }
+        write!(
+            f,
+            "{} moves, {} blunders (threshold {:.0}%)",
+            self.moves.len(),
+            self.blunders().count(),
+            self.threshold * 100.0
+        )
+    }
+}
+
+/// Evaluates every legal alternative at each move of the replay and flags the
+/// moves whose value was more than threshold (a fraction, e.g. 0.2) below the
+/// best alternative.
+pub fn analyze(replay: &Replay, eval: &mut impl Evaluator, threshold: f32) -> Report {
+    let moves = replay
+        .iter()
+        .enumerate()
+        .map(|(turn, (g, played))| analyze_move(turn, &g, played, eval, threshold))
+        .collect();
+    Report { threshold, moves }
+}
+
+fn analyze_move(
+    turn: usize,
+    g: &Game,
+    played: Move,
+    eval: &mut impl Evaluator,
+    threshold: f32,
+) -> MoveAnalysis {
+    let values = Move::ALL.map(|mv| eval.evaluate(g, mv));
+    let mut best = played;
+    let mut best_v = f32::MIN;
+    for (&mv, v) in Move::ALL.iter().zip(values) {
+        if let Some(v) = v {
+            if v > best_v {
+                best = mv;
+                best_v = v;
+            }
+        }
+    }
+    let played_v = values[Move::ALL.iter().position(|&m| m == played).unwrap()].unwrap_or(0.0);
+    let loss = if best_v > 0.0 {
+        ((best_v - played_v) / best_v).max(0.0)
+    } else {
+        0.0
+    };
+    MoveAnalysis {
+        turn,
+        played,
+        best,
+        values,
+        loss,
+        blunder: loss > threshold,
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::analysis::{analyze, Evaluator};
+    use crate::game::{Error, Game, Move};
+    use crate::replay::Replay;
+    use crate::tile::Tile;
+
+    // Likes Up, tolerates Left, hates everything else.
+    struct Fixed;
+
+    impl Evaluator for Fixed {
+        fn evaluate(&mut self, g: &Game, mv: Move) -> Option<f32> {
+            let mut g = g.clone();
+            if g.apply(mv) == Err(Error::IllegalMove) {
+                return None;
+            }
+            Some(match mv {
+                Move::Up => 10.0,
+                Move::Left => 9.0,
+                _ => 1.0,
+            })
+        }
+    }
+
+    #[test]
+    fn flags_blunders() {
+        let mut g = Game::new(Tile::new(192), 12);
+        let mut r = Replay::new(g.clone());
+        for mv in [Move::Right, Move::Down, Move::Left, Move::Up] {
+            if g.apply(mv) != Err(Error::IllegalMove) {
+                r.push(mv);
+            }
+        }
+        let report = analyze(&r, &mut Fixed, 0.2);
+        assert_eq!(report.moves.len(), r.moves().len());
+        for m in &report.moves {
+            let good_legal = m.value(Move::Up).is_some() || m.value(Move::Left).is_some();
+            let want = good_legal && (m.played == Move::Right || m.played == Move::Down);
+            assert_eq!(m.blunder, want, "{:?}", m);
+        }
+    }
+}
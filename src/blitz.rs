@@ -0,0 +1,232 @@
+use rand::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::{
+    game::{Error, Game, Move, NextHint},
+    replay::Replay,
+};
+
+/// How much time a blitz game allows.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Clock {
+    /// The whole game must be played within the budget; the game ends when it
+    /// runs out.
+    Total(Duration),
+    /// Each move must be made within limit.  When it runs out, a random move
+    /// is played for the player if auto_move, otherwise the game ends.
+    PerMove { limit: Duration, auto_move: bool },
+}
+
+/// The outcome of a finished blitz game.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BlitzResult {
+    pub score: u64,
+    pub elapsed: Duration,
+    pub moves: usize,
+    /// The number of moves played for the player on timeouts.
+    pub auto_moves: usize,
+    /// Whether the game ended because time ran out, rather than on the board.
+    pub timed_out: bool,
+}
+
+/// A game played against a clock.  The caller passes in the current time, so
+/// every front-end enforces the clock the same way: call tick regularly while
+/// waiting for input, and apply with each move.
+#[derive(Clone, Debug)]
+pub struct Blitz {
+    g: Game,
+    replay: Replay,
+    clock: Clock,
+    started: Instant,
+    // When the current move's clock started.
+    turn: Instant,
+    ended: Option<Instant>,
+    auto_moves: usize,
+    timed_out: bool,
+}
+
+impl Blitz {
+    /// Starts the clock on g at now.
+    pub fn new(g: Game, clock: Clock, now: Instant) -> Self {
+        let ended = (!g.can_move()).then_some(now);
+        Self {
+            replay: Replay::new(g.clone()),
+            g,
+            clock,
+            started: now,
+            turn: now,
+            ended,
+            auto_moves: 0,
+            timed_out: false,
+        }
+    }
+    pub fn game(&self) -> &Game {
+        &self.g
+    }
+    pub fn clock(&self) -> Clock {
+        self.clock
+    }
+    /// Every move played so far, including those played on timeouts.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+    pub fn is_over(&self) -> bool {
+        self.ended.is_some()
+    }
+    /// The time played so far; fixed once the game is over.
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        self.ended
+            .unwrap_or(now)
+            .saturating_duration_since(self.started)
+    }
+    /// The time left on the clock: for the game, or for the current move.
+    pub fn remaining(&self, now: Instant) -> Duration {
+        let now = self.ended.unwrap_or(now);
+        match self.clock {
+            Clock::Total(budget) => {
+                budget.saturating_sub(now.saturating_duration_since(self.started))
+            }
+            Clock::PerMove { limit, .. } => {
+                limit.saturating_sub(now.saturating_duration_since(self.turn))
+            }
+        }
+    }
+    /// Returns the result once the game is over.
+    pub fn result(&self) -> Option<BlitzResult> {
+        let ended = self.ended?;
+        Some(BlitzResult {
+            score: self.g.score(),
+            elapsed: ended.saturating_duration_since(self.started),
+            moves: self.replay.moves().len(),
+            auto_moves: self.auto_moves,
+            timed_out: self.timed_out,
+        })
+    }
+    /// Applies any timeouts up to now, playing random moves or ending the
+    /// game as the clock says.  Returns whether anything changed.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let mut changed = false;
+        while self.ended.is_none() && self.remaining(now).is_zero() {
+            changed = true;
+            match self.clock {
+                Clock::PerMove {
+                    limit,
+                    auto_move: true,
+                } => {
+                    // The random move is played when the clock ran out, and
+                    // the next move's clock starts then.
+                    self.turn += limit;
+                    self.auto_moves += 1;
+                    let mut moves = Move::ALL;
+                    moves.shuffle(&mut thread_rng());
+                    let mv = moves
+                        .into_iter()
+                        .find(|&mv| self.g.clone().apply(mv) != Err(Error::IllegalMove));
+                    if let Some(mv) = mv {
+                        let _ = self.play(mv, self.turn);
+                    }
+                }
+                Clock::Total(budget) => {
+                    self.timed_out = true;
+                    self.ended = Some(self.started + budget);
+                }
+                Clock::PerMove { limit, .. } => {
+                    self.timed_out = true;
+                    self.ended = Some(self.turn + limit);
+                }
+            }
+        }
+        changed
+    }
+    /// Plays mv at now, after applying any timeouts.  Returns GameOver if the
+    /// game had already ended or ends with this move, and IllegalMove if
+    /// nothing moved.
+    pub fn apply(&mut self, mv: Move, now: Instant) -> Result<NextHint, Error> {
+        self.tick(now);
+        if self.ended.is_some() {
+            return Err(Error::GameOver);
+        }
+        self.play(mv, now)
+    }
+    fn play(&mut self, mv: Move, now: Instant) -> Result<NextHint, Error> {
+        if !self.g.can_move() {
+            return Err(Error::GameOver);
+        }
+        let res = self.g.apply(mv);
+        if res == Err(Error::IllegalMove) {
+            return res;
+        }
+        self.replay.push(mv);
+        self.turn = now;
+        if res == Err(Error::GameOver) {
+            self.ended = Some(now);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::blitz::{Blitz, Clock};
+    use crate::game::{Error, Game, Move};
+    use crate::tile::Tile;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    // Returns a legal move in g.
+    fn legal(g: &Game) -> Move {
+        Move::ALL
+            .into_iter()
+            .find(|&mv| g.clone().apply(mv) != Err(Error::IllegalMove))
+            .unwrap()
+    }
+
+    #[test]
+    fn total() {
+        let t = Instant::now();
+        let mut b = Blitz::new(Game::new(Tile::EMPTY, 0), Clock::Total(secs(10)), t);
+        assert_eq!(b.remaining(t + secs(4)), secs(6));
+        let mv = legal(b.game());
+        assert!(b.apply(mv, t + secs(4)).is_ok());
+        assert!(!b.tick(t + secs(9)));
+        assert!(b.tick(t + secs(12)));
+        assert!(b.is_over());
+        assert_eq!(b.apply(mv, t + secs(13)), Err(Error::GameOver));
+        let r = b.result().unwrap();
+        assert!(r.timed_out);
+        assert_eq!((r.elapsed, r.moves), (secs(10), 1));
+        assert_eq!(b.remaining(t + secs(20)), secs(0));
+    }
+
+    #[test]
+    fn per_move() {
+        let t = Instant::now();
+        let clock = Clock::PerMove {
+            limit: secs(2),
+            auto_move: false,
+        };
+        let mut b = Blitz::new(Game::new(Tile::EMPTY, 0), clock, t);
+        let mv = legal(b.game());
+        assert!(b.apply(mv, t + secs(1)).is_ok());
+        assert_eq!(b.remaining(t + secs(2)), secs(1));
+        assert_eq!(b.apply(mv, t + secs(4)), Err(Error::GameOver));
+        assert_eq!(b.result().unwrap().elapsed, secs(3));
+
+        let clock = Clock::PerMove {
+            limit: secs(2),
+            auto_move: true,
+        };
+        let mut b = Blitz::new(Game::new(Tile::EMPTY, 0), clock, t);
+        assert!(b.tick(t + secs(5)));
+        assert!(b.is_over() || b.replay().moves().len() == 2);
+        assert_eq!(b.replay().end().as_ref(), Ok(b.game()));
+    }
+}
@@ -1,177 +1,888 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, ops::Deref, str::FromStr};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    error::Error,
+    game::Move,
+    merge::{Classic, MergeRule},
+    tile::Tile,
+};
+
+/// The largest supported board width or height.
+pub const MAX_SIZE: usize = 6;
+
 /// A board for threes, holding the Board currently in play and allowing its
-/// manipulation.  Board::default() provides an empty board.
-#[derive(Copy, Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Board(pub [u32; 16], pub u32);
-
-impl std::fmt::Display for Board {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{:?}\n{:?}\n{:?}\n{:?}",
-            &self.0[0..4],
-            &self.0[4..8],
-            &self.0[8..12],
-            &self.0[12..16]
+/// manipulation.  Boards may be anywhere from 1x1 to MAX_SIZE x MAX_SIZE;
+/// Board::default() provides an empty 4x4 board.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "Cells", try_from = "Cells")
+)]
+pub struct Board {
+    // Row by row; cells past width * height are always 0.
+    cells: [Tile; MAX_SIZE * MAX_SIZE],
+    width: usize,
+    height: usize,
+    max: Tile,
+}
+
+// The serialized form of a Board.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Cells {
+    width: usize,
+    height: usize,
+    cells: Vec<Tile>,
+}
+
+impl From<Board> for Cells {
+    fn from(b: Board) -> Self {
+        Self {
+            width: b.width,
+            height: b.height,
+            cells: b.cells().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<Cells> for Board {
+    type Error = Error;
+
+    fn try_from(c: Cells) -> Result<Self, Error> {
+        if !valid_size(c.width, c.height) || c.cells.len() != c.width * c.height {
+            return Err(Error::InvalidBoard(format!(
+                "{} cells do not make a {}x{} board",
+                c.cells.len(),
+                c.width,
+                c.height
+            )));
+        }
+        Ok(Self::from_tiles(c.width, c.height, &c.cells))
+    }
+}
+
+pub(crate) fn valid_size(width: usize, height: usize) -> bool {
+    (1..=MAX_SIZE).contains(&width) && (1..=MAX_SIZE).contains(&height)
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::empty(4, 4)
+    }
+}
+
+/// Writes the board on one line, rows separated by "/" and cells by ".", with
+/// 0 for an empty cell: "3.12.1.2/6.6.1.2/12.6.3.1/0.3.6.6".  The alternate
+/// form ({:#}) is a grid with one row per line, right-aligned, with "." for
+/// an empty cell.  Both parse back with FromStr.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.max.to_string().len();
+        for (i, row) in self.rows().enumerate() {
+            if i > 0 {
+                if f.alternate() {
+                    writeln!(f)?;
+                } else {
+                    write!(f, "/")?;
+                }
+            }
+            for (j, t) in row.iter().enumerate() {
+                if f.alternate() {
+                    let sep = if j > 0 { " " } else { "" };
+                    write!(f, "{}{:>width$}", sep, t)?;
+                } else {
+                    let sep = if j > 0 { "." } else { "" };
+                    write!(f, "{}{}", sep, t.value())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses either form written by Display.  Empty cells may be written as 0,
+/// "." in a grid, or nothing in the one-line form ("3..1.2").  Cell values
+/// are not checked against any merge rule.
+impl FromStr for Board {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let bad = |msg: String| Err(Error::InvalidBoard(msg));
+        let s = s.trim();
+        if s.is_empty() {
+            return bad("empty board".into());
+        }
+        // Rows are split by lines or by "/"; a lone row is a grid if its
+        // cells are spaced out or it is a single empty cell.
+        let grid =
+            s.contains('\n') || (!s.contains('/') && (s.contains(char::is_whitespace) || s == "."));
+        let rows: Vec<Vec<&str>> = if grid {
+            s.lines()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| l.split_whitespace().collect())
+                .collect()
+        } else {
+            s.split('/').map(|r| r.split('.').collect()).collect()
+        };
+        let (width, height) = (rows[0].len(), rows.len());
+        if !valid_size(width, height) {
+            return bad(format!("a {}x{} board is not supported", width, height));
+        }
+        let mut cells = Vec::with_capacity(width * height);
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return bad(format!(
+                    "row {} has {} cells, but row 1 has {}",
+                    i + 1,
+                    row.len(),
+                    width
+                ));
+            }
+            for cell in row {
+                let v = match *cell {
+                    "" | "." => 0,
+                    c => match c.parse() {
+                        Ok(v) => v,
+                        Err(_) => return bad(format!("bad cell {:?} in row {}", c, i + 1)),
+                    },
+                };
+                cells.push(v);
+            }
+        }
+        Ok(Self::from_cells(width, height, &cells))
+    }
+}
+
+/// One of the eight ways to rotate or reflect a board.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Symmetry {
+    Identity,
+    /// A quarter turn clockwise.
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirrors left and right.
+    FlipHorizontal,
+    /// Mirrors top and bottom.
+    FlipVertical,
+    /// Reflects across the main diagonal, so rows become columns.
+    Transpose,
+    /// Reflects across the other diagonal.
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    /// The symmetry that undoes this one.
+    pub fn inverse(self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            s => s,
+        }
+    }
+    pub fn swaps_axes(self) -> bool {
+        matches!(
+            self,
+            Symmetry::Rotate90
+                | Symmetry::Rotate270
+                | Symmetry::Transpose
+                | Symmetry::AntiTranspose
         )
     }
+    // Returns where row, col of a width x height board ends up.
+    fn map(self, row: usize, col: usize, width: usize, height: usize) -> (usize, usize) {
+        let (r, c) = (height - 1 - row, width - 1 - col);
+        match self {
+            Symmetry::Identity => (row, col),
+            Symmetry::Rotate90 => (col, r),
+            Symmetry::Rotate180 => (r, c),
+            Symmetry::Rotate270 => (c, row),
+            Symmetry::FlipHorizontal => (row, c),
+            Symmetry::FlipVertical => (r, col),
+            Symmetry::Transpose => (col, row),
+            Symmetry::AntiTranspose => (c, r),
+        }
+    }
+    /// The move that does on the transformed board what mv does on the
+    /// original, e.g. to augment (position, move) training data.
+    pub fn map_move(self, mv: Move) -> Move {
+        // Follow the cell next to the centre of a 3x3 board in mv's direction.
+        let (row, col) = match mv {
+            Move::Up => (0, 1),
+            Move::Down => (2, 1),
+            Move::Left => (1, 0),
+            Move::Right => (1, 2),
+        };
+        match self.map(row, col, 3, 3) {
+            (0, _) => Move::Up,
+            (2, _) => Move::Down,
+            (_, 0) => Move::Left,
+            _ => Move::Right,
+        }
+    }
 }
 
-// board layout:
+/// A set of board positions in increasing order, such as the cells a move
+/// vacated, held inline so that moves do not allocate.  Dereferences to the
+/// slice of positions.
+#[derive(Copy, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "Vec<usize>", try_from = "Vec<usize>")
+)]
+pub struct Positions {
+    idx: [usize; MAX_SIZE * MAX_SIZE],
+    len: usize,
+}
+
+impl Positions {
+    pub const EMPTY: Positions = Positions {
+        idx: [0; MAX_SIZE * MAX_SIZE],
+        len: 0,
+    };
+
+    pub fn as_slice(&self) -> &[usize] {
+        &self.idx[..self.len]
+    }
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+    // Adds pos, which must be larger than every position already held.
+    pub(crate) fn push(&mut self, pos: usize) {
+        self.idx[self.len] = pos;
+        self.len += 1;
+    }
+}
+
+impl Default for Positions {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl Deref for Positions {
+    type Target = [usize];
+
+    fn deref(&self) -> &[usize] {
+        self.as_slice()
+    }
+}
+
+impl<'a> IntoIterator for &'a Positions {
+    type Item = &'a usize;
+    type IntoIter = core::slice::Iter<'a, usize>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl PartialEq for Positions {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for Positions {}
+
+impl fmt::Debug for Positions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+/// Panics if given more positions than the largest board has cells, or not
+/// in increasing order.
+impl FromIterator<usize> for Positions {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut p = Self::EMPTY;
+        for pos in iter {
+            assert!(p.last().is_none_or(|&l| l < pos), "positions out of order");
+            p.push(pos);
+        }
+        p
+    }
+}
+
+impl From<Positions> for Vec<usize> {
+    fn from(p: Positions) -> Self {
+        p.to_vec()
+    }
+}
+
+impl TryFrom<Vec<usize>> for Positions {
+    type Error = Error;
+
+    fn try_from(v: Vec<usize>) -> Result<Self, Error> {
+        if v.len() > MAX_SIZE * MAX_SIZE || !v.is_sorted_by(|a, b| a < b) {
+            return Err(Error::Invalid(format!("bad positions {:?}", v)));
+        }
+        Ok(v.into_iter().collect())
+    }
+}
+
+// board layout (4x4; other sizes follow the same row by row order):
 // [  0,  1,  2,  3,
 //    4,  5,  6,  7,
 //    8,  9, 10, 11,
 //   12, 13, 14, 15]
-const fn rev1(x: [usize; 4]) -> [usize; 4] {
-    [x[3], x[2], x[1], x[0]]
-}
-const fn rev(x: [[usize; 4]; 4]) -> [[usize; 4]; 4] {
-    [rev1(x[0]), rev1(x[1]), rev1(x[2]), rev1(x[3])]
-}
-
-const LEFTS: [[usize; 4]; 4] = [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11], [12, 13, 14, 15]];
-const RIGHTS: [[usize; 4]; 4] = rev(LEFTS);
-const UPS: [[usize; 4]; 4] = [[0, 4, 8, 12], [1, 5, 9, 13], [2, 6, 10, 14], [3, 7, 11, 15]];
-const DOWNS: [[usize; 4]; 4] = rev(UPS);
 
-fn combines(a: u32, b: u32) -> bool {
-    (a == 1 && b == 2) || (a == 2 && b == 1) || (a != 1 && a != 2 && a == b)
+// The cell indices of one row or column, in the order they squish.
+#[derive(Copy, Clone)]
+struct Line {
+    idx: [usize; MAX_SIZE],
+    len: usize,
 }
 
 impl Board {
+    /// Builds a 4x4 board from the values of its cells, listed row by row.
+    pub fn new(cells: [u32; 16]) -> Self {
+        Self::from_cells(4, 4, &cells)
+    }
+    /// Builds an empty board.  Panics unless both dimensions are between 1
+    /// and MAX_SIZE.
+    pub fn empty(width: usize, height: usize) -> Self {
+        assert!(valid_size(width, height), "bad board size {width}x{height}");
+        Self {
+            cells: [Tile::EMPTY; MAX_SIZE * MAX_SIZE],
+            width,
+            height,
+            max: Tile::EMPTY,
+        }
+    }
+    /// Builds a board from the values of its cells, listed row by row.
+    /// Panics if the size is invalid or cells does not hold width * height
+    /// values.
+    pub fn from_cells(width: usize, height: usize, cells: &[u32]) -> Self {
+        let tiles: Vec<Tile> = cells.iter().map(|&v| Tile::new(v)).collect();
+        Self::from_tiles(width, height, &tiles)
+    }
+    /// Like from_cells, but from tiles.
+    pub fn from_tiles(width: usize, height: usize, cells: &[Tile]) -> Self {
+        let mut b = Self::empty(width, height);
+        assert_eq!(cells.len(), width * height, "wrong number of cells");
+        b.cells[..cells.len()].copy_from_slice(cells);
+        b.max = cells.iter().copied().max().unwrap_or(Tile::EMPTY);
+        b
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// The cells, row by row.
+    pub fn cells(&self) -> &[Tile] {
+        &self.cells[..self.width * self.height]
+    }
+    /// Returns InvalidTile for the first cell that rule does not allow.
+    pub fn validate_with<M: MergeRule + ?Sized>(&self, rule: &M) -> Result<(), Error> {
+        match self
+            .cells()
+            .iter()
+            .find(|&&t| !t.is_empty() && !rule.is_tile(t))
+        {
+            Some(t) => Err(Error::InvalidTile(t.value())),
+            None => Ok(()),
+        }
+    }
+    /// The tile at row, col, or None if that is off the board.
+    pub fn get(&self, row: usize, col: usize) -> Option<Tile> {
+        (row < self.height && col < self.width).then(|| self.cells[row * self.width + col])
+    }
+    /// The rows, top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &[Tile]> {
+        self.cells().chunks(self.width)
+    }
+    /// The columns, left to right, each from top to bottom.
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = Tile> + '_> {
+        (0..self.width).map(move |col| self.cells().iter().skip(col).step_by(self.width).copied())
+    }
+    /// Every cell as (row, col, tile), row by row.
+    pub fn grid(&self) -> impl Iterator<Item = (usize, usize, Tile)> + '_ {
+        let w = self.width;
+        self.cells()
+            .iter()
+            .enumerate()
+            .map(move |(i, &t)| (i / w, i % w, t))
+    }
+    /// The positions of the empty cells.
+    pub fn empty_cells(&self) -> impl Iterator<Item = usize> + '_ {
+        self.cells()
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.is_empty())
+            .map(|(i, _)| i)
+    }
+    /// The number of cells holding tile.
+    pub fn count(&self, tile: Tile) -> usize {
+        self.cells().iter().filter(|&&t| t == tile).count()
+    }
+    /// The positions next to pos (above, below, left and right) that are on
+    /// the board.
+    pub fn neighbors(&self, pos: usize) -> impl Iterator<Item = usize> {
+        let (w, n) = (self.width, self.width * self.height);
+        let (row, col) = (pos / w, pos % w);
+        [
+            (row > 0).then(|| pos - w),
+            (pos + w < n).then_some(pos + w),
+            (col > 0).then(|| pos - 1),
+            (col + 1 < w).then_some(pos + 1),
+        ]
+        .into_iter()
+        .flatten()
+    }
+    /// The board as seen through sym; rotating a quarter turn or transposing
+    /// swaps the width and height.
+    pub fn transformed(&self, sym: Symmetry) -> Board {
+        let (w, h) = (self.width, self.height);
+        let (w2, h2) = if sym.swaps_axes() { (h, w) } else { (w, h) };
+        let mut b = Board::empty(w2, h2);
+        for (row, col, t) in self.grid() {
+            let (r, c) = sym.map(row, col, w, h);
+            b.cells[r * w2 + c] = t;
+        }
+        b.max = self.max;
+        b
+    }
+    /// The board under each of Symmetry::ALL.
+    pub fn symmetries(&self) -> [Board; 8] {
+        Symmetry::ALL.map(|sym| self.transformed(sym))
+    }
+    /// The same board for every symmetry of a position (the least of its
+    /// symmetries), e.g. for hashing positions regardless of orientation.
+    pub fn canonical(&self) -> Board {
+        self.symmetries()
+            .into_iter()
+            .min_by(|a, b| (a.height, a.cells()).cmp(&(b.height, b.cells())))
+            .unwrap()
+    }
     pub fn can_move(&self) -> bool {
-        for i in 0..16 {
-            if self.0[i] == 0 {
-                return true;
-            }
+        self.can_move_with(&Classic)
+    }
+    /// Reports whether any move is possible under the given merge rule.
+    pub fn can_move_with<M: MergeRule + ?Sized>(&self, rule: &M) -> bool {
+        let (w, h) = (self.width, self.height);
+        let c = self.cells();
+        if c.contains(&Tile::EMPTY) {
+            return true;
         }
-        for i in (0..16).step_by(4) {
-            for j in i..i + 3 {
-                if combines(self.0[j], self.0[j + 1]) {
-                    return true;
-                }
+        for i in 0..w * h {
+            if i % w + 1 < w && (rule.can_merge(c[i], c[i + 1]) || rule.can_merge(c[i + 1], c[i])) {
+                return true;
             }
-        }
-        for i in 0..4 {
-            for j in (i..i + 12).step_by(4) {
-                if combines(self.0[j], self.0[j + 4]) {
-                    return true;
-                }
+            if i + w < w * h && (rule.can_merge(c[i], c[i + w]) || rule.can_merge(c[i + w], c[i])) {
+                return true;
             }
         }
         false
     }
-    pub(crate) fn left(&mut self) -> Vec<usize> {
-        LEFTS.into_iter().filter_map(|x| self.squish(&x)).collect()
+    // Returns the lines to squish for a move: rows for left/right, columns for
+    // up/down, each ordered so that it squishes towards its first index.
+    fn lines(&self, horizontal: bool, reverse: bool) -> impl Iterator<Item = Line> {
+        let (w, h) = (self.width, self.height);
+        let (n, len) = if horizontal { (h, w) } else { (w, h) };
+        (0..n).map(move |i| {
+            let mut l = Line {
+                idx: [0; MAX_SIZE],
+                len,
+            };
+            for j in 0..len {
+                let j2 = if reverse { len - 1 - j } else { j };
+                l.idx[j] = if horizontal { i * w + j2 } else { j2 * w + i };
+            }
+            l
+        })
+    }
+    /// The total score of the tiles on the board under the given merge rule.
+    pub fn score_with<M: MergeRule + ?Sized>(&self, rule: &M) -> u64 {
+        self.cells().iter().map(|&t| rule.tile_score(t)).sum()
+    }
+    // Moves the board under the given merge rule and returns the last cell of
+    // every line that moved, which is the one vacated.
+    pub(crate) fn shift<M: MergeRule + ?Sized>(&mut self, mv: Move, rule: &M) -> Positions {
+        self.shift_with(mv, rule, &mut |_, _| {})
+    }
+    // Like shift, but calls on_merge with the position and value of every
+    // merged tile.
+    pub(crate) fn shift_with<M: MergeRule + ?Sized>(
+        &mut self,
+        mv: Move,
+        rule: &M,
+        on_merge: &mut dyn FnMut(usize, Tile),
+    ) -> Positions {
+        let (horizontal, reverse) = match mv {
+            Move::Up => (false, false),
+            Move::Down => (false, true),
+            Move::Left => (true, false),
+            Move::Right => (true, true),
+        };
+        self.lines(horizontal, reverse)
+            .filter_map(|l| {
+                let line = &l.idx[..l.len];
+                if rule.slides_fully() {
+                    self.slide(line, rule, on_merge)
+                } else {
+                    self.squish(line, rule, on_merge)
+                }
+            })
+            .collect()
+    }
+    pub(crate) fn left(&mut self) -> Positions {
+        self.shift(Move::Left, &Classic)
     }
-    pub(crate) fn right(&mut self) -> Vec<usize> {
-        RIGHTS.into_iter().filter_map(|x| self.squish(&x)).collect()
+    pub(crate) fn right(&mut self) -> Positions {
+        self.shift(Move::Right, &Classic)
     }
-    pub(crate) fn up(&mut self) -> Vec<usize> {
-        UPS.into_iter().filter_map(|x| self.squish(&x)).collect()
+    pub(crate) fn up(&mut self) -> Positions {
+        self.shift(Move::Up, &Classic)
     }
-    pub(crate) fn down(&mut self) -> Vec<usize> {
-        DOWNS.into_iter().filter_map(|x| self.squish(&x)).collect()
+    pub(crate) fn down(&mut self) -> Positions {
+        self.shift(Move::Down, &Classic)
     }
-    pub(crate) fn max_val(&self) -> u32 {
-        self.1
+    pub(crate) fn max_val(&self) -> Tile {
+        self.max
     }
     // Sets [pos] to c if it is empty and returns true if successful.
-    pub(crate) fn set(&mut self, pos: usize, c: u32) -> bool {
-        if pos < 16 && self.0[pos] == 0 {
-            self.0[pos] = c;
-            if c > self.1 {
-                self.1 = c;
+    pub(crate) fn set(&mut self, pos: usize, c: Tile) -> bool {
+        if pos < self.width * self.height && self.cells[pos].is_empty() {
+            self.cells[pos] = c;
+            if c > self.max {
+                self.max = c;
             }
             return true;
         }
         false
     }
-    pub(crate) fn board(&self) -> [u32; 16] {
-        self.0
-    }
 
-    // Squish the elements described by the array to the left.  Returns
-    // Some(x[3]) if the items were shifted or None if not.
-    fn squish(&mut self, x: &[usize; 4]) -> Option<usize> {
+    // Squish the elements described by the line to its start.  Returns
+    // Some(last index) if the items were shifted or None if not.
+    fn squish<M: MergeRule + ?Sized>(
+        &mut self,
+        x: &[usize],
+        rule: &M,
+        on_merge: &mut dyn FnMut(usize, Tile),
+    ) -> Option<usize> {
         let mut shiftable = false;
         let mut shifted = false;
-        for i in 0..3 {
+        for i in 0..x.len() - 1 {
             let idx = x[i];
             let idxp1 = x[i + 1];
-            if shiftable || self.0[idx] == 0 {
-                if self.0[idxp1] != 0 {
+            if shiftable || self.cells[idx].is_empty() {
+                if !self.cells[idxp1].is_empty() {
                     shifted = true;
                 }
-                self.0[idx] = self.0[idxp1];
+                self.cells[idx] = self.cells[idxp1];
                 shiftable = true;
-            } else if combines(self.0[idx], self.0[idxp1]) {
-                let v = self.0[idx] + self.0[idxp1];
-                if v > self.1 {
-                    self.1 = v;
+            } else if rule.can_merge(self.cells[idx], self.cells[idxp1]) {
+                let v = rule.merged_value(self.cells[idx], self.cells[idxp1]);
+                if v > self.max {
+                    self.max = v;
                 }
-                self.0[idx] = v;
+                self.cells[idx] = v;
+                on_merge(idx, v);
                 shifted = true;
                 shiftable = true;
             }
         }
+        let last = x[x.len() - 1];
         if shifted {
-            self.0[x[3]] = 0;
-            return Some(x[3]);
+            self.cells[last] = Tile::EMPTY;
+            return Some(last);
         }
         None
     }
+
+    // Slides the elements described by the line as far as they go towards its
+    // start, merging each tile at most once.  Returns Some(last index) if
+    // anything moved or None if not.
+    fn slide<M: MergeRule + ?Sized>(
+        &mut self,
+        x: &[usize],
+        rule: &M,
+        on_merge: &mut dyn FnMut(usize, Tile),
+    ) -> Option<usize> {
+        let mut out = [Tile::EMPTY; MAX_SIZE];
+        let mut n = 0;
+        let mut merged = false;
+        for &idx in x {
+            let v = self.cells[idx];
+            if v.is_empty() {
+                continue;
+            }
+            if n > 0 && !merged && rule.can_merge(out[n - 1], v) {
+                out[n - 1] = rule.merged_value(out[n - 1], v);
+                self.max = self.max.max(out[n - 1]);
+                on_merge(x[n - 1], out[n - 1]);
+                merged = true;
+            } else {
+                out[n] = v;
+                n += 1;
+                merged = false;
+            }
+        }
+        let mut shifted = false;
+        for (&idx, &v) in x.iter().zip(&out) {
+            shifted |= self.cells[idx] != v;
+            self.cells[idx] = v;
+        }
+        shifted.then(|| x[x.len() - 1])
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::board::Board;
+    use crate::board::{Board, Positions, Symmetry};
+    use crate::error::Error;
+    use crate::game::Move;
+    use crate::merge::{Classic, Fibonacci, MergeRule, Twenty48};
+    use crate::tile::Tile;
+
+    fn board(s: &str) -> Board {
+        s.parse().unwrap()
+    }
 
     #[test]
     fn left() {
-        let mut b = Board([3, 0, 1, 2, 6, 6, 1, 2, 12, 6, 3, 1, 0, 3, 6, 6], 12);
-        assert_eq!(b.left(), vec![3, 7, 15]);
+        let mut b = board("3.0.1.2/6.6.1.2/12.6.3.1/0.3.6.6");
+        assert_eq!(b.left()[..], [3, 7, 15]);
 
-        let want = Board([3, 1, 2, 0, 12, 1, 2, 0, 12, 6, 3, 1, 3, 6, 6, 0], 12);
+        let want = board("3.1.2.0/12.1.2.0/12.6.3.1/3.6.6.0");
         assert_eq!(b, want);
         assert!(b.can_move());
     }
     #[test]
     fn right() {
-        let mut b = Board([3, 0, 1, 2, 6, 6, 1, 3, 12, 6, 3, 1, 0, 3, 6, 6], 12);
-        assert_eq!(b.right(), vec![0, 4, 12]);
+        let mut b = board("3.0.1.2/6.6.1.3/12.6.3.1/0.3.6.6");
+        assert_eq!(b.right()[..], [0, 4, 12]);
 
-        let want = Board([0, 3, 0, 3, 0, 12, 1, 3, 12, 6, 3, 1, 0, 0, 3, 12], 12);
+        let want = board("0.3.0.3/0.12.1.3/12.6.3.1/0.0.3.12");
         assert_eq!(b, want);
         assert!(b.can_move());
     }
     #[test]
     fn up() {
-        let mut b = Board([3, 0, 1, 2, 6, 6, 1, 1, 12, 6, 3, 3, 12, 3, 6, 6], 12);
-        assert_eq!(b.up(), vec![12, 13, 15]);
+        let mut b = board("3.0.1.2/6.6.1.1/12.6.3.3/12.3.6.6");
+        assert_eq!(b.up()[..], [12, 13, 15]);
 
-        let want = Board([3, 6, 1, 3, 6, 6, 1, 3, 24, 3, 3, 6, 0, 0, 6, 0], 24);
+        let want = board("3.6.1.3/6.6.1.3/24.3.3.6/0.0.6.0");
         assert_eq!(b, want);
         assert!(b.can_move());
     }
     #[test]
     fn down() {
-        let mut b = Board([3, 0, 1, 2, 6, 6, 1, 1, 12, 6, 3, 3, 12, 3, 6, 6], 12);
-        assert_eq!(b.down(), vec![0, 1, 3]);
+        let mut b = board("3.0.1.2/6.6.1.1/12.6.3.3/12.3.6.6");
+        assert_eq!(b.down()[..], [0, 1, 3]);
 
-        let want = Board([0, 0, 1, 0, 3, 0, 1, 3, 6, 12, 3, 3, 24, 3, 6, 6], 24);
+        let want = board("0.0.1.0/3.0.1.3/6.12.3.3/24.3.6.6");
         assert_eq!(b, want);
         assert!(b.can_move());
     }
     #[test]
     fn down_bug() {
-        let mut b = Board([1, 0, 2, 0, 6, 0, 0, 1, 12, 3, 6, 0, 3072, 12, 6, 2], 3072);
-        assert_eq!(b.down(), vec![2, 3]);
+        let mut b = board("1.0.2.0/6.0.0.1/12.3.6.0/3072.12.6.2");
+        assert_eq!(b.down()[..], [2, 3]);
     }
     #[test]
     fn can_move() {
-        let b = Board([3, 1, 3, 1, 2, 3, 2, 3, 3, 1, 3, 1, 2, 3, 2, 3], 3);
+        let b = board("3.1.3.1/2.3.2.3/3.1.3.1/2.3.2.3");
         assert!(!b.can_move());
     }
+    #[test]
+    fn sizes() {
+        let mut b = board("1.2.0/3.3.3/0.0.6");
+        assert_eq!(b.left()[..], [2, 5, 8]);
+        assert_eq!(b, board("3.0.0/6.3.0/0.6.0"));
+
+        let mut b = board("1.3.0/2.3.6");
+        assert_eq!(b.up()[..], [3, 4, 5]);
+        assert_eq!(b, board("3.6.6/0.0.0"));
+        assert_eq!(b.down()[..], [0, 1, 2]);
+        assert_eq!(b.right()[..], [3]);
+        assert_eq!(b, board("0.0.0/0.3.12"));
+        assert_eq!(b.max_val(), 12);
+
+        let mut b = Board::empty(6, 6);
+        assert!(b.set(35, Tile::ONE));
+        assert!(!b.set(36, Tile::ONE));
+        assert_eq!(b.up()[..], [35]);
+        assert_eq!(b.cells()[29], 1);
+        assert_eq!(b.width() * b.height(), b.cells().len());
+    }
+    #[test]
+    fn grid() {
+        let b = board("1.0.3/0.6.3");
+        assert_eq!(b.get(1, 2), Some(Tile::THREE));
+        assert_eq!((b.get(2, 0), b.get(0, 3)), (None, None));
+        assert_eq!(b.rows().nth(1).unwrap(), [0, 6, 3]);
+        let cols: Vec<Vec<Tile>> = b.columns().map(|c| c.collect()).collect();
+        assert_eq!(cols, [vec![1, 0], vec![0, 6], vec![3, 3]]);
+        assert_eq!(b.grid().nth(4), Some((1, 1, Tile::new(6))));
+        assert_eq!(b.empty_cells().collect::<Vec<_>>(), [1, 3]);
+        assert_eq!(b.count(Tile::THREE), 2);
+        assert_eq!(b.neighbors(0).collect::<Vec<_>>(), [3, 1]);
+        assert_eq!(b.neighbors(4).collect::<Vec<_>>(), [1, 3, 5]);
+    }
+    #[test]
+    fn symmetries() {
+        let b = board("1.2.3/6.12.24");
+        let r = b.transformed(Symmetry::Rotate90);
+        assert_eq!(r, board("6.1/12.2/24.3"));
+        for sym in Symmetry::ALL {
+            let t = b.transformed(sym);
+            assert_eq!(t.transformed(sym.inverse()), b, "{:?}", sym);
+            assert_eq!(t.canonical(), b.canonical());
+            for mv in Move::ALL {
+                let (mut moved, mut t2) = (b, t);
+                moved.shift(mv, &Classic);
+                t2.shift(sym.map_move(mv), &Classic);
+                assert_eq!(moved.transformed(sym), t2, "{:?} {:?}", sym, mv);
+            }
+        }
+        let distinct: std::collections::HashSet<_> = b.symmetries().into_iter().collect();
+        assert_eq!(distinct.len(), 8);
+    }
+    #[test]
+    fn notation() {
+        let b = board("3.0.1.2/6.6.1.2/12.6.3.1/0.3.192.6");
+        assert_eq!(b.to_string(), "3.0.1.2/6.6.1.2/12.6.3.1/0.3.192.6");
+        let grid = format!("{:#}", b);
+        assert_eq!(
+            grid,
+            "  3   .   1   2\n  6   6   1   2\n 12   6   3   1\n  .   3 192   6"
+        );
+        assert_eq!(grid.parse(), Ok(b));
+        assert_eq!(board("3..1.2/6.6.1.2/12.6.3.1/0.3.192.6"), b);
+        assert_eq!(
+            board("1.2/3.6/0.12"),
+            Board::from_cells(2, 3, &[1, 2, 3, 6, 0, 12])
+        );
+        for (w, h, cells) in [
+            (1, 1, &[0][..]),
+            (1, 1, &[3]),
+            (3, 1, &[0, 0, 0]),
+            (3, 1, &[3, 0, 12]),
+            (1, 3, &[0, 6, 0]),
+        ] {
+            let b = Board::from_cells(w, h, cells);
+            assert_eq!(board(&b.to_string()), b);
+            assert_eq!(board(&format!("{:#}", b)), b);
+        }
+
+        for (s, err) in [
+            ("", "empty board"),
+            ("1.2/3", "row 2 has 1 cells, but row 1 has 2"),
+            ("1.2/3.x", "bad cell \"x\" in row 2"),
+            ("1.2.3.4.5.6.7", "a 7x1 board is not supported"),
+        ] {
+            assert_eq!(s.parse::<Board>(), Err(Error::InvalidBoard(err.into())));
+        }
+    }
+    #[test]
+    fn positions() {
+        let p: Positions = [1, 4, 35].into_iter().collect();
+        assert_eq!(p[..], [1, 4, 35]);
+        assert_eq!(Positions::try_from(vec![1, 4, 35]), Ok(p));
+        assert!(Positions::try_from(vec![4, 1]).is_err());
+        assert!(Positions::try_from(vec![0; 37]).is_err());
+        assert!(Positions::default().is_empty());
+        #[cfg(feature = "serde")]
+        assert_eq!(serde_json::to_string(&p).unwrap(), "[1,4,35]");
+    }
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let b = board("1.2/3.6/0.12");
+        let s = serde_json::to_string(&b).unwrap();
+        assert_eq!(s, r#"{"width":2,"height":3,"cells":[1,2,3,6,0,12]}"#);
+        assert_eq!(serde_json::from_str::<Board>(&s).unwrap(), b);
+        assert!(serde_json::from_str::<Board>(r#"{"width":2,"height":3,"cells":[1]}"#).is_err());
+    }
+    // Plays each move on its board under rule and checks the vacated cells and
+    // the resulting board.
+    fn check_moves<M: MergeRule>(rule: &M, cases: &[(Board, Move, &[usize], Board)]) {
+        for (b, mv, vacated, want) in cases {
+            let mut b = *b;
+            assert_eq!(b.shift(*mv, rule)[..], **vacated, "{:?}\n{}", mv, b);
+            assert_eq!(b, *want, "{:?}", mv);
+        }
+    }
+    #[test]
+    fn classic_moves() {
+        let b = board("3.0.1.2/6.6.1.2/12.6.3.1/0.3.6.6");
+        check_moves(
+            &Classic,
+            &[
+                (
+                    b,
+                    Move::Left,
+                    &[3, 7, 15],
+                    board("3.1.2.0/12.1.2.0/12.6.3.1/3.6.6.0"),
+                ),
+                (
+                    b,
+                    Move::Up,
+                    &[13, 15],
+                    board("3.6.1.2/6.6.1.3/12.3.3.6/0.0.6.0"),
+                ),
+            ],
+        );
+    }
+    #[test]
+    fn fibonacci_moves() {
+        let b = board("1.1.2.3/5.8.0.3/2.2.3.5/0.1.3.13");
+        check_moves(
+            &Fibonacci,
+            &[
+                (
+                    b,
+                    Move::Left,
+                    &[3, 7, 11, 15],
+                    board("2.2.3.0/13.0.3.0/2.5.5.0/1.3.13.0"),
+                ),
+                (
+                    b,
+                    Move::Down,
+                    &[0, 1, 2, 3],
+                    board("0.0.0.0/1.1.2.3/5.8.3.8/2.3.3.13"),
+                ),
+            ],
+        );
+        assert!(!board("1.3.1.3/3.1.3.1/1.3.1.3/3.1.3.1").can_move_with(&Fibonacci));
+        assert_eq!(b.score_with(&Fibonacci), 3 + 9 + 27 + 3 + 3 + 9 + 3 + 81);
+    }
+    #[test]
+    fn twenty48() {
+        let mut b = board("2.2.2.2/0.4.0.4/8.0.0.0/2.4.8.16");
+        assert_eq!(b.shift(Move::Left, &Twenty48)[..], [3, 7]);
+        let want = board("4.4.0.0/8.0.0.0/8.0.0.0/2.4.8.16");
+        assert_eq!(b, want);
+        assert_eq!(b.shift(Move::Right, &Twenty48)[..], [0, 4, 8]);
+        assert_eq!(b.cells()[3], 8);
+        assert!(b.can_move_with(&Twenty48));
+
+        let b = board("1.2.1.2/2.1.2.1/1.2.1.2/2.1.2.1");
+        assert!(!b.can_move_with(&Twenty48));
+        assert!(b.can_move());
+    }
 }
@@ -0,0 +1,281 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, str::FromStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+use crate::{
+    error::Error,
+    game::{Game, Move},
+    replay::Replay,
+    rules::Rules,
+    tile::Tile,
+};
+
+/// The version of the daily rules.  Bump it whenever a change to the rules or
+/// the engine would deal a different game from the same seed, so that results
+/// from different versions are never compared.
+pub const RULES_VERSION: u32 = 3;
+
+/// The boost tile every daily game starts with.
+pub const BOOST: Tile = Tile::three_times_pow2(6);
+
+/// A calendar date (proleptic Gregorian), written as YYYY-MM-DD.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    /// Returns the date, or None if it does not exist.
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        let leap =
+            year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+        let days = match month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => return None,
+        };
+        (1..=days)
+            .contains(&day)
+            .then_some(Self { year, month, day })
+    }
+    /// Today's date in UTC.
+    #[cfg(feature = "std")]
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self::from_days((secs / 86400) as i64)
+    }
+    // Converts days since 1970-01-01 to a date; see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    fn from_days(days: i64) -> Self {
+        let z = days + 719468;
+        let (era, doe) = (z.div_euclid(146097), z.rem_euclid(146097));
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut parts = s.splitn(3, '-').map(|p| p.parse::<u16>().ok());
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Some(y)), Some(Some(m)), Some(Some(d))) if m <= 12 && d <= 31 => {
+                Self::new(y, m as u8, d as u8)
+                    .ok_or_else(|| Error::Invalid(format!("no such date: {}", s)))
+            }
+            _ => Err(Error::Invalid(format!("expected YYYY-MM-DD, got {:?}", s))),
+        }
+    }
+}
+
+impl From<Date> for String {
+    fn from(d: Date) -> Self {
+        d.to_string()
+    }
+}
+
+impl TryFrom<String> for Date {
+    type Error = Error;
+    fn try_from(s: String) -> Result<Self, Error> {
+        s.parse()
+    }
+}
+
+/// Returns the rng seed for the daily game on date under the given rules
+/// version.
+pub fn seed(date: Date, version: u32) -> u64 {
+    let packed = (date.year as u64) << 16 | (date.month as u64) << 8 | date.day as u64;
+    splitmix(packed ^ (version as u64) << 32)
+}
+
+/// Returns the daily game for date: the standard rules, with a BOOST tile at
+/// a cell picked by the seed.
+pub fn challenge(date: Date, version: u32) -> Game {
+    let rules = Rules::standard();
+    let seed = seed(date, version);
+    let boostpos = (splitmix(seed) % (rules.width * rules.height) as u64) as usize;
+    Game::seeded(rules, BOOST, boostpos, seed)
+}
+
+// SplitMix64's output function; spreads nearby inputs over the whole range.
+fn splitmix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Returns a hash of the moves of a replay (FNV-1a over one byte per move).
+pub fn replay_hash(moves: &[Move]) -> u64 {
+    moves.iter().fold(0xcbf29ce484222325, |h, &mv| {
+        let b = Move::ALL.iter().position(|&m| m == mv).unwrap() as u64;
+        (h ^ b).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A shareable claim of a daily result, written as
+/// `YYYY-MM-DD.v<version>.<score>.<replay hash in hex>`.  Anyone holding the
+/// replay can check it with verify.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResultCode {
+    pub date: Date,
+    pub version: u32,
+    pub score: u64,
+    pub hash: u64,
+}
+
+impl ResultCode {
+    /// Returns the code for replay, or None if the replay does not start from
+    /// the daily game for date or contains an illegal move.
+    pub fn new(date: Date, version: u32, replay: &Replay) -> Option<Self> {
+        if *replay.start() != challenge(date, version) {
+            return None;
+        }
+        let end = replay.end().ok()?;
+        Some(Self {
+            date,
+            version,
+            score: end.score(),
+            hash: replay_hash(replay.moves()),
+        })
+    }
+    /// Reports whether replay is a game of this code's daily challenge that
+    /// reaches its score.
+    pub fn verify(&self, replay: &Replay) -> bool {
+        Self::new(self.date, self.version, replay) == Some(*self)
+    }
+}
+
+impl fmt::Display for ResultCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.v{}.{}.{:016x}",
+            self.date, self.version, self.score, self.hash
+        )
+    }
+}
+
+impl FromStr for ResultCode {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let bad = || Error::Invalid(format!("expected YYYY-MM-DD.vN.SCORE.HASH, got {:?}", s));
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        let [date, version, score, hash] = parts[..] else {
+            return Err(bad());
+        };
+        Ok(Self {
+            date: date.parse()?,
+            version: version
+                .strip_prefix('v')
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(bad)?,
+            score: score.parse().map_err(|_| bad())?,
+            hash: u64::from_str_radix(hash, 16).map_err(|_| bad())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::daily::{challenge, Date, ResultCode, RULES_VERSION};
+    use crate::game::{Error, Move};
+    use crate::replay::Replay;
+
+    #[test]
+    fn dates() {
+        assert_eq!(Date::from_days(0), Date::new(1970, 1, 1).unwrap());
+        assert_eq!(Date::from_days(11016), Date::new(2000, 2, 29).unwrap());
+        assert_eq!(Date::from_days(20744), Date::new(2026, 10, 18).unwrap());
+        assert_eq!("2024-02-29".parse(), Ok(Date::new(2024, 2, 29).unwrap()));
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("2023-13-01".parse::<Date>().is_err());
+        assert!("yesterday".parse::<Date>().is_err());
+        assert_eq!(Date::new(2026, 1, 5).unwrap().to_string(), "2026-01-05");
+    }
+
+    #[test]
+    fn same_game_each_day() {
+        let d = Date::new(2026, 10, 18).unwrap();
+        assert_eq!(challenge(d, RULES_VERSION), challenge(d, RULES_VERSION));
+        let next = Date::new(2026, 10, 19).unwrap();
+        assert_ne!(challenge(d, RULES_VERSION), challenge(next, RULES_VERSION));
+        assert_ne!(challenge(d, RULES_VERSION), challenge(d, RULES_VERSION + 1));
+    }
+
+    #[test]
+    fn result_codes() {
+        let d = Date::new(2026, 10, 18).unwrap();
+        let mut g = challenge(d, RULES_VERSION);
+        let mut r = Replay::new(g.clone());
+        for mv in Move::ALL.iter().cycle().take(30) {
+            if !g.can_move() {
+                break;
+            }
+            if g.apply(*mv) != Err(Error::IllegalMove) {
+                r.push(*mv);
+            }
+        }
+        let code = ResultCode::new(d, RULES_VERSION, &r).unwrap();
+        assert_eq!(code.score, g.score());
+        assert_eq!(code.to_string().parse(), Ok(code));
+        assert!(code.verify(&r));
+
+        let inflated = ResultCode {
+            score: code.score + 3,
+            ..code
+        };
+        assert!(!inflated.verify(&r));
+        let other_day = ResultCode {
+            date: Date::new(2026, 10, 19).unwrap(),
+            ..code
+        };
+        assert!(!other_day.verify(&r));
+        r.truncate(r.moves().len() - 1);
+        assert!(!code.verify(&r));
+        assert!("2026-10-18.1.2.3".parse::<ResultCode>().is_err());
+    }
+}
@@ -1,33 +1,57 @@
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 use rand::prelude::*;
 
-#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+use crate::tile::Tile;
+
+#[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(crate) struct Deck {
-    pub(crate) contents: Vec<u32>,
+    pub(crate) contents: Vec<Tile>,
+}
+
+// A clone keeps the full capacity, so that it refills without allocating.
+impl Clone for Deck {
+    fn clone(&self) -> Self {
+        let mut contents = Vec::with_capacity(self.contents.capacity());
+        contents.extend_from_slice(&self.contents);
+        Self { contents }
+    }
 }
 
 impl Deck {
-    // Returns the number of 1s, 2s, and 3s.
-    pub fn counts(&self) -> [u32; 3] {
-        self.contents.iter().fold([0; 3], |mut acc, v| {
-            acc[(v - 1) as usize] = acc[(v - 1) as usize] + 1;
-            acc
-        })
-    }
-    pub(crate) fn new(rng: &mut impl Rng) -> Self {
-        Self {
-            contents: Self::newv(rng),
-        }
+    // Returns how many of each card value are left, in increasing order of
+    // value.
+    pub fn counts(&self) -> Vec<(Tile, u32)> {
+        let mut v = self.contents.clone();
+        v.sort();
+        v.chunk_by(|a, b| a == b)
+            .map(|c| (c[0], c.len() as u32))
+            .collect()
     }
-    fn newv(rng: &mut impl Rng) -> Vec<u32> {
-        let mut v = vec![1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3];
-        v.shuffle(rng);
-        v
+    pub(crate) fn new(cards: &[Tile], rng: &mut (impl Rng + ?Sized)) -> Self {
+        let mut d = Self {
+            contents: Vec::with_capacity(cards.len()),
+        };
+        d.refill(cards, rng);
+        d
     }
-    pub(crate) fn next(&mut self, rng: &mut impl Rng) -> u32 {
-        if self.contents.len() == 0 {
-            self.contents = Self::newv(rng);
+    // Builds a deck holding exactly the given cards, in random order.
+    pub(crate) fn from_cards(mut contents: Vec<Tile>, rng: &mut (impl Rng + ?Sized)) -> Self {
+        contents.shuffle(rng);
+        Self { contents }
+    }
+    // Adds cards to the deck and shuffles it, reusing the deck's allocation.
+    pub(crate) fn refill(&mut self, cards: &[Tile], rng: &mut (impl Rng + ?Sized)) {
+        self.contents.extend_from_slice(cards);
+        self.contents.shuffle(rng);
+    }
+    // Draws a card, first refilling the deck with cards if it is empty.
+    pub(crate) fn next(&mut self, cards: &[Tile], rng: &mut (impl Rng + ?Sized)) -> Tile {
+        if self.contents.is_empty() {
+            self.refill(cards, rng);
         }
         self.contents.pop().unwrap()
     }
@@ -35,16 +59,21 @@ impl Deck {
 
 #[cfg(test)]
 mod tests {
-    use rand::thread_rng;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
 
     use crate::deck::Deck;
+    use crate::rules::Rules;
+    use crate::tile::Tile;
 
     #[test]
     fn next() {
-        let mut d = Deck::new(&mut thread_rng());
+        let cards = Rules::standard().deck;
+        let mut rng = Pcg32::seed_from_u64(0);
+        let mut d = Deck::new(&cards, &mut rng);
         let mut got = Vec::new();
         for _ in 0..12 {
-            got.push(d.next(&mut thread_rng()));
+            got.push(d.next(&cards, &mut rng));
         }
         assert_eq!(got.iter().filter(|&x| *x == 1).count(), 4);
         assert_eq!(got.iter().filter(|&x| *x == 2).count(), 4);
@@ -53,11 +82,20 @@ mod tests {
 
         got.clear();
         for _ in 0..12 {
-            got.push(d.next(&mut thread_rng()));
+            got.push(d.next(&cards, &mut rng));
         }
         assert_eq!(got.iter().filter(|&x| *x == 1).count(), 4);
         assert_eq!(got.iter().filter(|&x| *x == 2).count(), 4);
         assert_eq!(got.iter().filter(|&x| *x == 3).count(), 4);
         assert_eq!(got.len(), 12);
     }
+    #[test]
+    fn counts() {
+        let cards = [3, 1, 6, 3].map(Tile::new).to_vec();
+        let d = Deck::from_cards(cards, &mut Pcg32::seed_from_u64(0));
+        assert_eq!(
+            d.counts(),
+            [(1, 1), (3, 2), (6, 1)].map(|(v, n)| (Tile::new(v), n))
+        );
+    }
 }
@@ -0,0 +1,75 @@
+use alloc::string::String;
+use core::fmt;
+
+/// Everything that can go wrong in the library: playing a move, building a
+/// game, or reading a board, tile, date or saved file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// A move that moved nothing.
+    IllegalMove,
+    /// The game is over: the move ended it, or it had already ended.
+    GameOver,
+    /// A spawn was resolved with a position that was not vacated or a value
+    /// that was not offered.
+    IllegalSpawn,
+    /// Rules that cannot be played; the message says what is wrong.
+    InvalidRules(String),
+    /// A value that is not a tile under the rules in use (for Tile itself,
+    /// under the Threes! rules).
+    InvalidTile(u32),
+    /// A position that is not on the board.
+    InvalidPosition(usize),
+    /// A board that is malformed or holds tiles the rules do not allow.
+    InvalidBoard(String),
+    /// Text or serialized data that could not be read, or that describes
+    /// something impossible.
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::IllegalMove => write!(f, "illegal move"),
+            Error::GameOver => write!(f, "the game is over"),
+            Error::IllegalSpawn => write!(f, "illegal spawn"),
+            Error::InvalidRules(msg) => write!(f, "invalid rules: {}", msg),
+            Error::InvalidTile(v) => write!(f, "{} is not a tile", v),
+            Error::InvalidPosition(pos) => write!(f, "position {} is not on the board", pos),
+            Error::InvalidBoard(msg) => write!(f, "invalid board: {}", msg),
+            Error::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        use alloc::string::ToString;
+        Error::Invalid(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+
+    #[test]
+    fn display() {
+        assert_eq!(Error::InvalidTile(5).to_string(), "5 is not a tile");
+        assert_eq!(
+            Error::InvalidPosition(16).to_string(),
+            "position 16 is not on the board"
+        );
+        let e: Box<dyn std::error::Error> = Box::new(Error::GameOver);
+        assert_eq!(e.to_string(), "the game is over");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn from_json() {
+        let e: Error = serde_json::from_str::<u32>("x").unwrap_err().into();
+        assert!(matches!(e, Error::Invalid(_)));
+    }
+}
@@ -0,0 +1,115 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+use crate::{
+    game::{Candidates, Move},
+    tile::Tile,
+};
+
+/// Something that happened in a game, as reported to its observers.  A move
+/// reports its merges, then Moved, then any deck or bonus events from drawing
+/// the next hint, then Spawned and, if it ended the game, GameOver.  They are
+/// all reported once the new tile has landed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Event {
+    /// The board moved in the given direction.  If the move was started with
+    /// Game::shift and its PendingSpawn is dropped or fails to resolve, the
+    /// move is undone and none of its events are reported.
+    Moved(Move),
+    /// Two tiles merged into value at position.
+    Merged { position: usize, value: Tile },
+    /// A new tile landed.
+    Spawned { position: usize, value: Tile },
+    /// A bonus tile was drawn as the next hint.
+    BonusDrawn(Candidates),
+    /// The deck ran out and a freshly shuffled one was started.
+    Reshuffled,
+    /// No move is possible any more.
+    GameOver,
+}
+
+/// Reacts to the events of a game; see Game::add_observer.  Any closure taking
+/// an &Event is an observer.
+pub trait Observer: Send + Sync {
+    fn notify(&mut self, e: &Event);
+}
+
+impl<F: FnMut(&Event) + Send + Sync> Observer for F {
+    fn notify(&mut self, e: &Event) {
+        self(e)
+    }
+}
+
+// The observers of a game.  They belong to the game they were added to: a
+// clone starts with none (so searches on copies stay quiet), they are not
+// saved, and they are ignored when comparing games.  While a move waits for
+// its new tile, its events are held back until the tile lands.
+#[derive(Default)]
+pub(crate) struct Observers {
+    list: Vec<Box<dyn Observer>>,
+    holding: bool,
+    held: Vec<Event>,
+}
+
+impl Observers {
+    pub(crate) fn add(&mut self, o: Box<dyn Observer>) {
+        self.list.push(o);
+    }
+    pub(crate) fn clear(&mut self) {
+        self.list.clear();
+        self.discard();
+    }
+    pub(crate) fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+    pub(crate) fn emit(&mut self, e: Event) {
+        if self.list.is_empty() {
+            return;
+        }
+        if self.holding {
+            self.held.push(e);
+            return;
+        }
+        for o in &mut self.list {
+            o.notify(&e);
+        }
+    }
+    // Holds back the events emitted from now on.
+    pub(crate) fn hold(&mut self) {
+        self.holding = true;
+    }
+    // Reports the held events and stops holding.
+    pub(crate) fn release(&mut self) {
+        self.holding = false;
+        let mut held = core::mem::take(&mut self.held);
+        for e in held.drain(..) {
+            self.emit(e);
+        }
+        self.held = held;
+    }
+    // Forgets the held events and stops holding.
+    pub(crate) fn discard(&mut self) {
+        self.holding = false;
+        self.held.clear();
+    }
+}
+
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl PartialEq for Observers {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Observers {}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Observers({})", self.list.len())
+    }
+}
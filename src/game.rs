@@ -1,127 +1,1159 @@
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{fmt, ops::Deref};
 use rand::prelude::*;
 use rand_pcg::Pcg32;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, rc::Rc};
 
-use crate::{board::Board, deck::Deck};
+pub use crate::error::Error;
+use crate::{
+    adversary::Adversary,
+    board::{Board, Positions},
+    event::{Event, Observer, Observers},
+    merge::MergeRule,
+    rules::Rules,
+    spawn::{Placement, SpawnPlacement, TileSource, Tiles},
+    tile::Tile,
+};
 
-#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
-pub struct Game {
-    rng: Box<Pcg32>,
+/// A game in progress.  R is the rng that shuffles the decks and places new
+/// tiles; it travels with the game, so a clone plays out the same way.  The
+/// tiles come from the deck and land at random unless set_tiles or
+/// set_placement say otherwise.  Observers added with add_observer hear about
+/// everything that happens; see Event.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(
+        try_from = "SavedGame<R>",
+        bound(deserialize = "R: RngCore + SeedableRng + Deserialize<'de>")
+    )
+)]
+pub struct Game<R = Pcg32> {
+    rng: Box<R>,
     b: Board,
-    d: Deck,
-    g: Vec<bool>,
-    next: RefCell<Rc<Vec<u32>>>,
+    tiles: Tiles,
+    #[cfg_attr(feature = "serde", serde(default))]
+    placement: Placement,
+    next: NextHint,
+    rules: Arc<Rules>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    observers: Observers,
+}
+
+// The serialized form of a Game, checked when read.
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+struct SavedGame<R> {
+    rng: Box<R>,
+    b: Board,
+    tiles: Tiles,
+    #[cfg_attr(feature = "serde", serde(default))]
+    placement: Placement,
+    next: NextHint,
+    rules: Arc<Rules>,
+}
+
+impl<R: RngCore + SeedableRng> TryFrom<SavedGame<R>> for Game<R> {
+    type Error = Error;
+
+    fn try_from(s: SavedGame<R>) -> Result<Self, Error> {
+        let g = Self {
+            rng: s.rng,
+            b: s.b,
+            tiles: s.tiles,
+            placement: s.placement,
+            next: s.next,
+            rules: s.rules,
+            observers: Observers::default(),
+        };
+        g.validate()?;
+        Ok(g)
+    }
+}
+
+/// The values a bonus tile may take: one to three of them, stored inline.
+/// Dereferences to the slice of values.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "Vec<Tile>", try_from = "Vec<Tile>")
+)]
+pub struct Candidates {
+    vals: [Tile; 3],
+    len: u8,
+}
+
+impl Candidates {
+    /// Panics unless there are one to three values.
+    pub fn new(vals: &[Tile]) -> Self {
+        assert!(
+            (1..=3).contains(&vals.len()),
+            "a bonus tile has 1 to 3 candidates"
+        );
+        let mut c = Self {
+            vals: [Tile::EMPTY; 3],
+            len: vals.len() as u8,
+        };
+        c.vals[..vals.len()].copy_from_slice(vals);
+        c
+    }
+    pub fn as_slice(&self) -> &[Tile] {
+        &self.vals[..self.len as usize]
+    }
+}
+
+impl Deref for Candidates {
+    type Target = [Tile];
+
+    fn deref(&self) -> &[Tile] {
+        self.as_slice()
+    }
+}
+
+impl From<Candidates> for Vec<Tile> {
+    fn from(c: Candidates) -> Self {
+        c.to_vec()
+    }
+}
+
+impl TryFrom<Vec<Tile>> for Candidates {
+    type Error = Error;
+
+    fn try_from(v: Vec<Tile>) -> Result<Self, Error> {
+        if v.is_empty() || v.len() > 3 {
+            return Err(Error::Invalid(format!(
+                "a bonus tile has 1 to 3 candidates, not {}",
+                v.len()
+            )));
+        }
+        Ok(Self::new(&v))
+    }
+}
+
+/// The upcoming tile as shown to the player.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NextHint {
+    /// A card from the deck, whose value is known.
+    Exact(Tile),
+    /// A bonus tile, which becomes one of the candidates (picked uniformly)
+    /// when it lands.
+    Bonus { candidates: Candidates },
+}
+
+impl NextHint {
+    /// A bonus hint.  Panics unless there are one to three candidates.
+    pub fn bonus(candidates: &[Tile]) -> Self {
+        NextHint::Bonus {
+            candidates: Candidates::new(candidates),
+        }
+    }
+    /// The values the tile may take.
+    pub fn values(&self) -> &[Tile] {
+        match self {
+            NextHint::Exact(v) => core::slice::from_ref(v),
+            NextHint::Bonus { candidates } => candidates,
+        }
+    }
+    pub fn is_bonus(&self) -> bool {
+        matches!(self, NextHint::Bonus { .. })
+    }
+    /// The chance that the tile lands as value.
+    pub fn probability(&self, value: Tile) -> f64 {
+        let vals = self.values();
+        if vals.contains(&value) {
+            1.0 / vals.len() as f64
+        } else {
+            0.0
+        }
+    }
+    /// Every value the tile may take, with its probability.
+    pub fn probabilities(&self) -> impl Iterator<Item = (Tile, f64)> + '_ {
+        let p = 1.0 / self.values().len() as f64;
+        self.values().iter().map(move |&v| (v, p))
+    }
+}
+
+/// Shows the value of an exact tile, and "+" for a bonus tile, as the game
+/// does; the alternate form ({:#}) lists a bonus tile's candidates instead.
+impl fmt::Display for NextHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NextHint::Exact(v) => write!(f, "{}", v),
+            NextHint::Bonus { candidates } if f.alternate() => {
+                let vals: Vec<String> = candidates.iter().map(|v| v.to_string()).collect();
+                write!(f, "+{}", vals.join("/"))
+            }
+            NextHint::Bonus { .. } => write!(f, "+"),
+        }
+    }
+}
+
+/// Everything needed to rebuild a game from an observed position, e.g. one
+/// copied from another Threes! game.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct State {
+    pub board: Board,
+    /// The cards left in the deck, in any order; empty means the next card
+    /// starts a fresh deck.
+    pub deck: Vec<Tile>,
+    /// The number of cards left in the bonus deck.
+    pub bonus_left: usize,
+    /// The number of bonus cards among them.
+    pub bonus_cards_left: usize,
+    pub next: NextHint,
+}
+
+impl State {
+    /// Checks that the state could occur under rules: the board has the rules'
+    /// size and only their tiles, the deck and next hold cards from their
+    /// deck, and the bonus deck is no longer than theirs nor holds more bonus
+    /// cards.
+    pub fn validate(&self, rules: &Rules) -> Result<(), Error> {
+        rules.validate()?;
+        self.validate_board(rules)?;
+        let card = |c: &Tile| {
+            if rules.deck.contains(c) {
+                Ok(())
+            } else {
+                Err(Error::Invalid(format!("{} is not a card in the deck", c)))
+            }
+        };
+        match &self.next {
+            NextHint::Exact(c) => card(c)?,
+            NextHint::Bonus { candidates } => {
+                if let Some(c) = candidates.iter().find(|&&c| !rules.merge.is_tile(c)) {
+                    return Err(Error::InvalidTile(c.value()));
+                }
+            }
+        }
+        self.deck.iter().try_for_each(card)?;
+        if self.bonus_left > rules.bonus_deck_len {
+            return Err(Error::Invalid(format!(
+                "{} cards left in a bonus deck of {}",
+                self.bonus_left, rules.bonus_deck_len
+            )));
+        }
+        if self.bonus_cards_left > self.bonus_left.min(rules.bonus_cards) {
+            return Err(Error::Invalid(format!(
+                "{} bonus cards left among {} cards, with {} per bonus deck",
+                self.bonus_cards_left, self.bonus_left, rules.bonus_cards
+            )));
+        }
+        Ok(())
+    }
+    // Checks that the board has the rules' size and only their tiles.
+    fn validate_board(&self, rules: &Rules) -> Result<(), Error> {
+        let b = &self.board;
+        if (b.width(), b.height()) != (rules.width, rules.height) {
+            return Err(Error::InvalidBoard(format!(
+                "the board is {}x{}, but the rules are for {}x{}",
+                b.width(),
+                b.height(),
+                rules.width,
+                rules.height
+            )));
+        }
+        b.validate_with(&rules.merge)
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub enum Error {
-    IllegalMove,
-    GameOver,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Move {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Move {
+    pub const ALL: [Move; 4] = [Move::Up, Move::Down, Move::Left, Move::Right];
 }
 
 impl Game {
-    pub fn new(boost: u32, boostpos: usize) -> Self {
-        let mut rng = Pcg32::from_entropy();
-        let d = Deck::new(&mut rng);
-        let mut s = Self {
+    /// Starts a standard game, with a boost tile (or Tile::EMPTY for none) at
+    /// boostpos.  Panics if the boost is not a tile or boostpos is off the
+    /// board; see try_with_rules.
+    #[cfg(feature = "std")]
+    pub fn new(boost: Tile, boostpos: usize) -> Self {
+        Self::with_rules(Rules::standard(), boost, boostpos)
+    }
+    /// Panics if the rules, boost or boostpos are invalid; see try_with_rules.
+    #[cfg(feature = "std")]
+    pub fn with_rules(rules: Rules, boost: Tile, boostpos: usize) -> Self {
+        Self::try_with_rules(rules, boost, boostpos).unwrap_or_else(|e| panic!("{}", e))
+    }
+    /// Like with_rules, but returns InvalidRules if the rules cannot be
+    /// played, InvalidTile if boost is not a tile under them, or
+    /// InvalidPosition if boostpos is off the board.
+    #[cfg(feature = "std")]
+    pub fn try_with_rules(rules: Rules, boost: Tile, boostpos: usize) -> Result<Self, Error> {
+        Self::try_with_rng(rules, boost, boostpos, Pcg32::from_entropy())
+    }
+    /// Builds a game from an arbitrary state.  The order of the remaining deck
+    /// and the position of the bonus card are chosen at random.  The state is
+    /// not checked against the rules; see try_from_state.
+    #[cfg(feature = "std")]
+    pub fn from_state(rules: Rules, s: State) -> Self {
+        Self::from_state_with_rng(rules, s, Pcg32::from_entropy())
+    }
+    /// Like from_state, but returns an error if the state could not occur
+    /// under the rules; see State::validate.
+    #[cfg(feature = "std")]
+    pub fn try_from_state(rules: Rules, s: State) -> Result<Self, Error> {
+        s.validate(&rules)?;
+        Ok(Self::from_state(rules, s))
+    }
+}
+
+impl<R: RngCore + SeedableRng> Game<R> {
+    /// Like with_rules, but every deal is determined by seed, so the same
+    /// arguments always produce the same game.
+    pub fn seeded(rules: Rules, boost: Tile, boostpos: usize, seed: u64) -> Self {
+        Self::try_seeded(rules, boost, boostpos, seed).unwrap_or_else(|e| panic!("{}", e))
+    }
+    /// Like seeded, but returns an error as try_with_rules does.
+    pub fn try_seeded(
+        rules: Rules,
+        boost: Tile,
+        boostpos: usize,
+        seed: u64,
+    ) -> Result<Self, Error> {
+        Self::try_with_rng(rules, boost, boostpos, R::seed_from_u64(seed))
+    }
+    /// Like from_state, but the unknowns and every later deal are determined
+    /// by seed.
+    pub fn from_state_seeded(rules: Rules, s: State, seed: u64) -> Self {
+        Self::from_state_with_rng(rules, s, R::seed_from_u64(seed))
+    }
+    /// Plays mv like apply, but lets adv decide where the tile lands and, for
+    /// bonus tiles, which of the candidates it is.
+    pub fn apply_with<A: Adversary<R> + ?Sized>(
+        &mut self,
+        mv: Move,
+        adv: &mut A,
+    ) -> Result<NextHint, Error> {
+        let p = self.shift(mv)?;
+        let (pos, c) = adv.choose(p.game(), p.open(), p.candidates().values());
+        p.resolve(pos, c)
+    }
+    /// Like Game::try_with_rules, but deals with rng.
+    pub fn try_with_rng(
+        rules: Rules,
+        boost: Tile,
+        boostpos: usize,
+        mut rng: R,
+    ) -> Result<Self, Error> {
+        rules.validate()?;
+        if !boost.is_empty() {
+            if !rules.merge.is_tile(boost) {
+                return Err(Error::InvalidTile(boost.value()));
+            }
+            if boostpos >= rules.width * rules.height {
+                return Err(Error::InvalidPosition(boostpos));
+            }
+        }
+        let mut tiles = Tiles::for_rules(&rules, !boost.is_empty(), &mut rng);
+        let mut b = Board::empty(rules.width, rules.height);
+        b.set(boostpos, boost);
+        // Deal out the initial cards into random spots; do not advance giants.
+        let n = b.cells().len();
+        let empty = b.empty_cells().count();
+        for _ in 0..rules.initial_tiles.min(empty) {
+            let c = tiles.card(&rules, &mut rng);
+            while !b.set(rng.gen_range(0..n), c) {}
+        }
+        let next = NextHint::Exact(tiles.card(&rules, &mut rng));
+        Ok(Self {
             rng: Box::new(rng),
-            b: Board::default(),
-            d: d,
-            // When starting a new game with boost, the giant deck is 21 blanks.
-            g: vec![false; 21],
-            next: RefCell::default(),
-        };
-        s.b.set(boostpos, boost);
-        if boost == 0 {
-            // No boost, so create giant deck.
-            s.new_giant();
+            b,
+            tiles,
+            placement: Placement::Random,
+            next,
+            rules: Arc::new(rules),
+            observers: Observers::default(),
+        })
+    }
+    /// Like Game::from_state, but deals with rng.
+    pub fn from_state_with_rng(rules: Rules, s: State, mut rng: R) -> Self {
+        Self {
+            tiles: Tiles::from_state(&rules, &s, &mut rng),
+            placement: Placement::Random,
+            rng: Box::new(rng),
+            b: s.board,
+            next: s.next,
+            rules: Arc::new(rules),
+            observers: Observers::default(),
         }
-        // Deal out 8 cards into random spots; do not advance giants.
-        for _ in 0..8 {
-            let c = s.d.next(&mut s.rng);
-            while !s.b.set(s.rng.gen_range(0..16), c) {}
+    }
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    // Checks that the game could occur under its rules, as State::validate
+    // does.  Sources other than the deck may deal tiles that are not cards.
+    fn validate(&self) -> Result<(), Error> {
+        let s = self.state();
+        if let Tiles::Deck(_) = self.tiles {
+            return s.validate(&self.rules);
         }
-        s.next = RefCell::new(Rc::new(vec![s.d.next(&mut s.rng)]));
-        s
+        self.rules.validate()?;
+        s.validate_board(&self.rules)?;
+        match self
+            .next
+            .values()
+            .iter()
+            .find(|&&c| !self.rules.merge.is_tile(c))
+        {
+            Some(c) => Err(Error::InvalidTile(c.value())),
+            None => Ok(()),
+        }
+    }
+    /// Returns the observable state of the game; see from_state.  Only a
+    /// game dealt from a deck has one; other games report an empty deck and
+    /// bonus deck.
+    pub fn state(&self) -> State {
+        let (deck, bonus_left, bonus_cards_left) = match &self.tiles {
+            Tiles::Deck(t) => (t.deck(), t.bonus_left(), t.bonus_cards_left()),
+            _ => (Vec::new(), 0, 0),
+        };
+        State {
+            board: self.b,
+            deck,
+            bonus_left,
+            bonus_cards_left,
+            next: self.next,
+        }
+    }
+    pub fn tiles(&self) -> &Tiles {
+        &self.tiles
+    }
+    /// Replaces the source of new tiles and draws the next hint from it.
+    pub fn set_tiles(&mut self, tiles: Tiles) {
+        self.tiles = tiles;
+        self.advance();
+    }
+    pub fn placement(&self) -> &Placement {
+        &self.placement
+    }
+    /// Replaces the rule deciding where new tiles land after a normal move.
+    pub fn set_placement(&mut self, placement: Placement) {
+        self.placement = placement;
+    }
+    /// Adds an observer to be told of every event from now on.  Observers stay
+    /// with this game: clones of it start without any.
+    pub fn add_observer(&mut self, o: impl Observer + 'static) {
+        self.observers.add(Box::new(o));
+    }
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
     }
     /// Replaces the rng in the game with a new one and shuffles all the
     /// unknowns (deck, giant position).
+    #[cfg(feature = "std")]
     pub fn rerand(&mut self) {
-        self.rng = Box::new(Pcg32::from_entropy());
-        self.d.contents.shuffle(&mut self.rng);
-        self.g.shuffle(&mut self.rng);
-    }
-    fn new_giant(&mut self) {
-        self.g = vec![false; 21];
-        self.g[self.rng.gen_range(0..21)] = true;
-    }
-    fn check_giant(&mut self) -> Option<Vec<u32>> {
-        if self.g.len() == 0 {
-            self.new_giant();
-        }
-        if !self.g.pop().unwrap() {
-            return None;
-        }
-        let m = self.b.max_val();
-        match m {
-            0 | 1 | 2 | 3 | 6 | 12 | 24 => return None,
-            48 => return Some(vec![6]),
-            96 => return Some(vec![6, 12]),
-            _ => {
-                let f = self.rng.gen_range(0..=((m / 192).ilog(2)));
-                let low = 6 * (2u32.pow(f));
-                Some(vec![low, low * 2, low * 4])
-            }
+        *self.rng = R::from_entropy();
+        self.shuffle_unknowns();
+    }
+    /// Like rerand, but seeds the new rng from rng, e.g. a fast per-thread rng
+    /// in a search.
+    pub fn rerand_with(&mut self, rng: &mut impl Rng) {
+        *self.rng = R::from_rng(rng).expect("rng failed");
+        self.shuffle_unknowns();
+    }
+    fn shuffle_unknowns(&mut self) {
+        self.tiles.shuffle(&mut *self.rng);
+    }
+    // Reports whether the next card drawn starts a fresh deck.
+    fn deck_empty(&self) -> bool {
+        matches!(&self.tiles, Tiles::Deck(t) if t.deck_empty())
+    }
+    // Tells the observers that next was drawn.
+    fn drew(&mut self, next: NextHint, refill: bool) {
+        match next {
+            NextHint::Exact(_) if refill => self.observers.emit(Event::Reshuffled),
+            NextHint::Exact(_) => {}
+            NextHint::Bonus { candidates } => self.observers.emit(Event::BonusDrawn(candidates)),
         }
     }
-    fn pull(&mut self) -> u32 {
-        // Determine next next.
+    // Draws a new next and returns the old one.
+    fn advance(&mut self) -> NextHint {
+        let refill = self.deck_empty();
         let next = self
-            .check_giant()
-            .unwrap_or_else(|| vec![self.d.next(&mut self.rng)]);
-        // Set next next as next and read the old next.
-        let next = self.next.replace(Rc::new(next));
-        // Determine next (pick from vec).
-        next[self.rng.gen_range(0..next.len())]
+            .tiles
+            .draw(&self.rules, self.b.max_val(), &mut *self.rng);
+        self.drew(next, refill);
+        core::mem::replace(&mut self.next, next)
     }
-    pub fn next(&self) -> Rc<Vec<u32>> {
-        self.next.borrow().clone()
+    fn pull(&mut self) -> Tile {
+        match self.advance() {
+            NextHint::Exact(c) => c,
+            NextHint::Bonus { candidates } => candidates[self.rng.gen_range(0..candidates.len())],
+        }
+    }
+    pub fn next(&self) -> NextHint {
+        self.next
+    }
+    /// Returns every value a bonus tile could have on the current board.
+    pub fn bonus_values(&self) -> Vec<Tile> {
+        let mut v: Vec<Tile> = self.rules.bonus_sets(self.b.max_val()).concat();
+        v.sort();
+        v.dedup();
+        v
     }
     pub fn board(&self) -> Board {
         self.b
     }
+    /// The score of the board under the game's merge rule.
+    pub fn score(&self) -> u64 {
+        self.b.score_with(&self.rules.merge)
+    }
     pub fn can_move(&self) -> bool {
-        self.b.can_move()
+        self.b.can_move_with(&self.rules.merge)
+    }
+    fn finish(&mut self, mv: Move) -> Result<NextHint, Error> {
+        self.shift(mv)?.resolve_random()
+    }
+    // Moves the board without spawning and returns the vacated positions.
+    // Its events are held until the new tile lands.
+    pub(crate) fn slide(&mut self, mv: Move) -> Positions {
+        self.observers.hold();
+        let observers = &mut self.observers;
+        let moved = self
+            .b
+            .shift_with(mv, &self.rules.merge, &mut |position, value| {
+                observers.emit(Event::Merged { position, value })
+            });
+        if moved.is_empty() {
+            self.observers.discard();
+        } else {
+            self.observers.emit(Event::Moved(mv));
+        }
+        if !self.rules.spawn_anywhere || moved.is_empty() {
+            return moved;
+        }
+        self.b.empty_cells().collect()
     }
-    fn finish(&mut self, open: Vec<usize>) -> Result<Rc<Vec<u32>>, Error> {
+    /// Moves the board in the given direction without placing the new tile,
+    /// which is left to the returned PendingSpawn.  Returns GameOver if the
+    /// game has already ended, or IllegalMove if nothing moved.
+    pub fn shift(&mut self, mv: Move) -> Result<PendingSpawn<'_, R>, Error> {
+        let before = self.b;
+        let open = self.slide(mv);
         if open.is_empty() {
-            return Err(Error::IllegalMove);
-        };
-        let next = self.pull();
-        self.b.set(open[self.rng.gen_range(0..open.len())], next);
+            // Nothing moves once the game is over.
+            return Err(if self.can_move() {
+                Error::IllegalMove
+            } else {
+                Error::GameOver
+            });
+        }
+        Ok(PendingSpawn {
+            g: self,
+            before,
+            open,
+            resolved: false,
+        })
+    }
+    // Places c (which must be one of next) at pos and draws a new next.
+    pub(crate) fn spawn(&mut self, pos: usize, c: Tile) -> Result<NextHint, Error> {
+        self.advance();
+        self.land(pos, c)
+    }
+    // Places c at pos once the next hint has been drawn.
+    fn land(&mut self, position: usize, value: Tile) -> Result<NextHint, Error> {
+        self.b.set(position, value);
+        self.observers.release();
+        self.observers.emit(Event::Spawned { position, value });
         if !self.can_move() {
+            self.observers.emit(Event::GameOver);
             return Err(Error::GameOver);
         }
         Ok(self.next())
     }
+    // Sets next as the new next, updating the tile source to match.  Returns
+    // false, changing nothing, if next could not have been drawn.
+    fn observe(&mut self, next: NextHint) -> bool {
+        let max = self.b.max_val();
+        let refill = self.deck_empty();
+        if !self.tiles.observe(&self.rules, max, next, &mut *self.rng) {
+            return false;
+        }
+        self.drew(next, refill);
+        self.next = next;
+        true
+    }
+    // Returns every possible next after the current one is used, with its
+    // probability.
+    fn hints(&self) -> Vec<(NextHint, f64)> {
+        self.tiles.hints(&self.rules, self.b.max_val())
+    }
     // up/down/left/right move in the given direction and return <next> unless
-    // the move was illegal or the game is over, in which case the appropriate
-    // error is returned.
-    pub fn up(&mut self) -> Result<Rc<Vec<u32>>, Error> {
-        let res = self.b.up();
-        self.finish(res)
-    }
-    pub fn down(&mut self) -> Result<Rc<Vec<u32>>, Error> {
-        let res = self.b.down();
-        self.finish(res)
-    }
-    pub fn left(&mut self) -> Result<Rc<Vec<u32>>, Error> {
-        let res = self.b.left();
-        self.finish(res)
-    }
-    pub fn right(&mut self) -> Result<Rc<Vec<u32>>, Error> {
-        let res = self.b.right();
-        self.finish(res)
+    // the move was illegal or the game is (or was already) over, in which case
+    // the appropriate error is returned.
+    pub fn up(&mut self) -> Result<NextHint, Error> {
+        self.finish(Move::Up)
+    }
+    pub fn down(&mut self) -> Result<NextHint, Error> {
+        self.finish(Move::Down)
+    }
+    pub fn left(&mut self) -> Result<NextHint, Error> {
+        self.finish(Move::Left)
+    }
+    pub fn right(&mut self) -> Result<NextHint, Error> {
+        self.finish(Move::Right)
+    }
+    pub fn apply(&mut self, mv: Move) -> Result<NextHint, Error> {
+        match mv {
+            Move::Up => self.up(),
+            Move::Down => self.down(),
+            Move::Left => self.left(),
+            Move::Right => self.right(),
+        }
+    }
+}
+
+/// One possible outcome of a PendingSpawn.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Spawn {
+    pub position: usize,
+    pub value: Tile,
+    pub probability: f64,
+}
+
+/// A move whose board shift has happened but whose new tile has not landed
+/// yet (a chance node).  Dropping it without resolving undoes the shift.
+#[derive(Debug)]
+pub struct PendingSpawn<'a, R = Pcg32> {
+    g: &'a mut Game<R>,
+    before: Board,
+    open: Positions,
+    resolved: bool,
+}
+
+impl<R: RngCore + SeedableRng> PendingSpawn<'_, R> {
+    /// The game with the board shifted and the new tile not yet placed.
+    pub fn game(&self) -> &Game<R> {
+        self.g
+    }
+    /// The vacated positions the new tile may land in.
+    pub fn open(&self) -> &[usize] {
+        &self.open
+    }
+    /// The values the new tile may have: the game's current next.
+    pub fn candidates(&self) -> NextHint {
+        self.g.next()
+    }
+    /// Every (position, value) the game's placement and next may give the
+    /// new tile, with its probability.  resolve also accepts the other open
+    /// positions.
+    pub fn spawns(&self) -> Vec<Spawn> {
+        let candidates = self.candidates();
+        self.g
+            .placement
+            .probabilities(&self.open)
+            .into_iter()
+            .flat_map(|(position, p)| {
+                candidates.probabilities().map(move |(value, q)| Spawn {
+                    position,
+                    value,
+                    probability: p * q,
+                })
+            })
+            .collect()
+    }
+    /// Every next the game may show once the tile has landed, with its
+    /// probability, as the game's tile source reports it.
+    pub fn hints(&self) -> Vec<(NextHint, f64)> {
+        self.g.hints()
+    }
+    /// Places value at position and draws the next hint from the rng.
+    /// Returns IllegalSpawn (undoing the shift) if the spawn is not one of
+    /// spawns(), or GameOver if no move is possible afterwards.
+    pub fn resolve(mut self, position: usize, value: Tile) -> Result<NextHint, Error> {
+        if !self.open.contains(&position) || self.candidates().probability(value) == 0.0 {
+            return Err(Error::IllegalSpawn);
+        }
+        self.resolved = true;
+        self.g.spawn(position, value)
+    }
+    /// Places value at position like resolve, but sets the following hint to
+    /// next instead of drawing it, updating the tile source to match.
+    /// Returns IllegalSpawn if next could not have been drawn.
+    pub fn resolve_observed(
+        mut self,
+        position: usize,
+        value: Tile,
+        next: NextHint,
+    ) -> Result<NextHint, Error> {
+        if !self.open.contains(&position)
+            || self.candidates().probability(value) == 0.0
+            || !self.g.observe(next)
+        {
+            return Err(Error::IllegalSpawn);
+        }
+        self.resolved = true;
+        self.g.land(position, value)
+    }
+    /// Places the new tile where the game's placement puts it, as a normal
+    /// move does.
+    pub fn resolve_random(mut self) -> Result<NextHint, Error> {
+        self.resolved = true;
+        let next = self.g.pull();
+        let pos = self.g.placement.place(&self.open, &mut *self.g.rng);
+        self.g.land(pos, next)
+    }
+}
+
+impl<R> Drop for PendingSpawn<'_, R> {
+    fn drop(&mut self) {
+        if !self.resolved {
+            self.g.b = self.before;
+            self.g.observers.discard();
+        }
+    }
+}
+
+#[cfg(test)]
+impl Game {
+    // Builds a game in an arbitrary position for tests; next is exact if it
+    // has one value, otherwise a bonus.
+    pub(crate) fn with_board(b: Board, next: &[u32]) -> Self {
+        let mut g = Self::seeded(Rules::standard(), Tile::EMPTY, 0, 0);
+        g.b = b;
+        let next: Vec<Tile> = next.iter().map(|&v| Tile::new(v)).collect();
+        g.next = match next[..] {
+            [c] => NextHint::Exact(c),
+            _ => NextHint::bonus(&next),
+        };
+        g
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{RngCore, SeedableRng};
+    use rand_pcg::Pcg32;
+    #[cfg(feature = "std")]
+    use std::sync::{Arc, Mutex};
+
+    use crate::adversary::WorstCase;
+    use crate::board::Board;
+    #[cfg(feature = "std")]
+    use crate::event::Event;
+    use crate::game::{Error, Game, Move, NextHint, State};
+    #[cfg(feature = "std")]
+    use crate::merge::Fibonacci;
+    use crate::rules::Rules;
+    use crate::spawn::{Placement, ScriptedPlacement, ScriptedTiles, Tiles};
+    use crate::tile::Tile;
+
+    fn board() -> Board {
+        Board::new([3, 0, 1, 2, 6, 6, 1, 2, 12, 6, 3, 1, 0, 3, 6, 6])
+    }
+
+    #[test]
+    fn send_sync() {
+        fn check<T: Send + Sync>() {}
+        check::<Game>();
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn sizes() {
+        let rules = Rules {
+            width: 3,
+            height: 5,
+            ..Rules::standard()
+        };
+        let mut g = Game::with_rules(rules, Tile::EMPTY, 0);
+        assert_eq!(g.board().cells().iter().filter(|&&c| c != 0).count(), 8);
+        for mv in Move::ALL.iter().cycle().take(1000) {
+            let before = g.board().cells().iter().filter(|&&c| c != 0).count();
+            if g.apply(*mv).is_ok() {
+                let after = g.board().cells().iter().filter(|&&c| c != 0).count();
+                assert!(after <= before + 1);
+            }
+        }
+        assert_eq!(g.board().cells().len(), 15);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn twenty48() {
+        let mut g = Game::with_rules(Rules::twenty48(), Tile::EMPTY, 0);
+        assert_eq!(g.board().cells().iter().filter(|&&c| c != 0).count(), 2);
+        assert!(matches!(g.tiles(), Tiles::Weighted(_)));
+        let mut moves = 0;
+        while g.can_move() && moves < 10000 {
+            for mv in Move::ALL {
+                if let Ok(p) = g.shift(mv) {
+                    let empty = p.game().board().cells().iter().filter(|&&c| c == 0).count();
+                    assert_eq!(p.open().len(), empty);
+                    let _ = p.resolve_random();
+                    moves += 1;
+                }
+            }
+        }
+        assert!(g
+            .board()
+            .cells()
+            .iter()
+            .all(|c| c.value().count_ones() <= 1));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn fibonacci() {
+        let mut g = Game::with_rules(Rules::fibonacci(), Tile::EMPTY, 0);
+        let mut moves = 0;
+        while g.can_move() && moves < 10000 {
+            for mv in Move::ALL {
+                if g.apply(mv).is_ok() {
+                    moves += 1;
+                }
+            }
+        }
+        let score = g.board().score_with(&Fibonacci);
+        assert_eq!(g.score(), score);
+        let fib: Vec<u32> = std::iter::successors(Some((1, 2)), |&(a, b)| Some((b, a + b)))
+            .map(|(a, _)| a)
+            .take(30)
+            .collect();
+        assert!(g
+            .board()
+            .cells()
+            .iter()
+            .all(|c| c.is_empty() || fib.contains(&c.value())));
+    }
+
+    #[test]
+    fn shift_resolve() {
+        let mut g = Game::with_board(board(), &[6, 12]);
+        let p = g.shift(Move::Left).unwrap();
+        assert_eq!(p.open(), &[3, 7, 15]);
+        let spawns = p.spawns();
+        assert_eq!(spawns.len(), 6);
+        let total: f64 = spawns.iter().map(|s| s.probability).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        let total: f64 = p.hints().iter().map(|(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(p.resolve(7, Tile::new(12)).is_ok());
+        assert_eq!(g.board().cells()[7], 12);
+        assert_eq!(g.board().cells()[3], 0);
+
+        let mut g = Game::with_board(board(), &[6, 12]);
+        g.set_placement(Placement::FirstSlot);
+        let spawns = g.shift(Move::Left).unwrap().spawns();
+        assert_eq!(spawns.len(), 2);
+        assert!(spawns
+            .iter()
+            .all(|s| s.position == 3 && s.probability == 0.5));
+    }
+
+    #[test]
+    fn unresolved() {
+        let mut g = Game::with_board(board(), &[2]);
+        let p = g.shift(Move::Left).unwrap();
+        assert_eq!(p.resolve(3, Tile::THREE), Err(Error::IllegalSpawn));
+        assert_eq!(g.board(), board());
+        let p = g.shift(Move::Left).unwrap();
+        assert_eq!(p.resolve(0, Tile::TWO), Err(Error::IllegalSpawn));
+        assert_eq!(g.board(), board());
+        drop(g.shift(Move::Right).unwrap());
+        assert_eq!(g.board(), board());
+        assert_eq!(
+            g.shift(Move::Up)
+                .unwrap()
+                .resolve(13, Tile::TWO)
+                .map(|_| ()),
+            Ok(())
+        );
+        assert_eq!(g.board().cells()[13], 2);
+    }
+
+    // Always returns 0, so every shuffle and pick is predictable.
+    #[derive(Clone, Debug)]
+    struct Zero;
+
+    impl RngCore for Zero {
+        fn next_u32(&mut self) -> u32 {
+            0
+        }
+        fn next_u64(&mut self) -> u64 {
+            0
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(0);
+        }
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            dest.fill(0);
+            Ok(())
+        }
+    }
+
+    impl SeedableRng for Zero {
+        type Seed = [u8; 0];
+        fn from_seed(_: [u8; 0]) -> Self {
+            Zero
+        }
+    }
+
+    #[test]
+    fn custom_rng() {
+        // Shuffling with Zero puts the bonus card on top of the bonus deck, so
+        // the first draw after the move is a bonus tile.
+        let mut cells = board().cells().to_vec();
+        cells[0] = Tile::new(384);
+        let s = State {
+            board: Board::from_tiles(4, 4, &cells),
+            deck: vec![Tile::ONE, Tile::THREE],
+            bonus_left: 21,
+            bonus_cards_left: 1,
+            next: NextHint::Exact(Tile::TWO),
+        };
+        let mut g = Game::from_state_with_rng(Rules::standard(), s.clone(), Zero);
+        assert_eq!(g.left(), Ok(NextHint::bonus(&[6, 12, 24].map(Tile::new))));
+        assert_eq!(g.board().cells()[3], 2);
+        let mut g = Game::<Zero>::from_state_seeded(Rules::standard(), s, 0);
+        assert!(g
+            .apply_with(Move::Left, &mut WorstCase { depth: 1 })
+            .is_ok());
+
+        let mut g = Game::<Pcg32>::seeded(Rules::standard(), Tile::EMPTY, 0, 0);
+        let mut g2 = g.clone();
+        g.rerand_with(&mut Pcg32::seed_from_u64(1));
+        g2.rerand_with(&mut Pcg32::seed_from_u64(1));
+        assert_eq!(g, g2);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn invalid() {
+        let standard = Rules::standard;
+        assert!(Game::try_with_rules(standard(), Tile::new(192), 15).is_ok());
+        assert_eq!(
+            Game::try_with_rules(standard(), Tile::new(5), 0),
+            Err(Error::InvalidTile(5))
+        );
+        assert_eq!(
+            Game::<Pcg32>::try_seeded(standard(), Tile::new(192), 16, 0),
+            Err(Error::InvalidPosition(16))
+        );
+        let rules = Rules {
+            deck: vec![],
+            ..standard()
+        };
+        assert!(matches!(
+            Game::try_with_rules(rules, Tile::EMPTY, 0),
+            Err(Error::InvalidRules(_))
+        ));
+        let rules = Rules {
+            bonus_window: 4,
+            ..standard()
+        };
+        assert!(rules.validate().is_err());
+        assert!(Rules::twenty48().validate().is_ok());
+
+        let mut s = Game::with_board(board(), &[2]).state();
+        assert_eq!(s.validate(&standard()), Ok(()));
+        s.next = NextHint::Exact(Tile::new(6));
+        assert!(matches!(s.validate(&standard()), Err(Error::Invalid(_))));
+        s.board = Board::new([5; 16]);
+        assert_eq!(
+            Game::try_from_state(standard(), s).map(|_| ()),
+            Err(Error::InvalidTile(5))
+        );
+    }
+
+    #[test]
+    fn scripted() {
+        let mut g = Game::with_board(board(), &[2]);
+        let bonus = NextHint::bonus(&[Tile::new(24)]);
+        let script = vec![bonus, NextHint::Exact(Tile::THREE)];
+        g.set_tiles(Tiles::Scripted(ScriptedTiles::new(script)));
+        g.set_placement(Placement::FirstSlot);
+        assert_eq!(g.next(), bonus);
+        assert_eq!(g.left(), Ok(NextHint::Exact(Tile::THREE)));
+        assert_eq!(g.board().cells()[3], 24);
+        let p = g.shift(Move::Down).unwrap();
+        assert_eq!(p.hints(), vec![(bonus, 1.0)]);
+        assert_eq!(p.open(), &[0, 1, 3]);
+        assert_eq!(p.resolve_random(), Ok(bonus));
+        assert_eq!(g.board().cells()[0], 3);
+        assert!(g.state().deck.is_empty());
+
+        g.set_placement(Placement::Scripted(ScriptedPlacement::new(vec![8])));
+        assert_eq!(g.right(), Ok(NextHint::Exact(Tile::THREE)));
+        assert_eq!(g.board().cells()[8], 24);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn events() {
+        let mut g = Game::from_state(
+            Rules::standard(),
+            State {
+                board: board(),
+                deck: vec![],
+                bonus_left: 3,
+                bonus_cards_left: 0,
+                next: NextHint::Exact(Tile::ONE),
+            },
+        );
+        g.set_placement(Placement::FirstSlot);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        g.add_observer(move |e: &Event| log.lock().unwrap().push(*e));
+        assert!(g.clone().left().is_ok());
+        drop(g.shift(Move::Left).unwrap());
+        let p = g.shift(Move::Left).unwrap();
+        assert_eq!(p.resolve(3, Tile::TWO), Err(Error::IllegalSpawn));
+        assert!(seen.lock().unwrap().is_empty());
+        assert!(g.left().is_ok());
+        assert_eq!(
+            seen.lock().unwrap()[..],
+            [
+                Event::Merged {
+                    position: 4,
+                    value: Tile::new(12)
+                },
+                Event::Moved(Move::Left),
+                Event::Reshuffled,
+                Event::Spawned {
+                    position: 3,
+                    value: Tile::ONE
+                },
+            ]
+        );
+
+        let mut g = Game::with_board(
+            Board::new([3, 6, 3, 6, 6, 3, 6, 3, 3, 6, 3, 6, 0, 6, 3, 6]),
+            &[6],
+        );
+        g.set_tiles(Tiles::Scripted(ScriptedTiles::new(vec![NextHint::bonus(
+            &[Tile::new(12)],
+        )])));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        g.add_observer(move |e: &Event| log.lock().unwrap().push(*e));
+        assert_eq!(g.left(), Err(Error::GameOver));
+        assert_eq!(g.right(), Err(Error::GameOver));
+        let seen = seen.lock().unwrap();
+        assert!(matches!(seen[1], Event::BonusDrawn(_)));
+        assert_eq!(seen.last(), Some(&Event::GameOver));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn observed() {
+        let mut g = Game::from_state(
+            Rules::standard(),
+            State {
+                board: board(),
+                deck: vec![Tile::TWO],
+                bonus_left: 3,
+                bonus_cards_left: 1,
+                next: NextHint::Exact(Tile::ONE),
+            },
+        );
+        let p = g.shift(Move::Left).unwrap();
+        assert_eq!(
+            p.resolve_observed(3, Tile::ONE, NextHint::Exact(Tile::THREE)),
+            Err(Error::IllegalSpawn)
+        );
+        let p = g.shift(Move::Left).unwrap();
+        assert_eq!(
+            p.resolve_observed(3, Tile::ONE, NextHint::Exact(Tile::TWO)),
+            Ok(NextHint::Exact(Tile::TWO))
+        );
+        let s = g.state();
+        assert!(s.deck.is_empty());
+        assert_eq!((s.bonus_left, s.bonus_cards_left), (2, 1));
+        assert_eq!(s.board.cells()[3], 1);
+
+        // The deck is empty, so anything from a fresh deck may come next.
+        let p = g.shift(Move::Up).unwrap();
+        assert_eq!(
+            p.resolve_observed(13, Tile::TWO, NextHint::Exact(Tile::THREE)),
+            Ok(NextHint::Exact(Tile::THREE))
+        );
+        let s = g.state();
+        assert_eq!(s.deck, vec![1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3]);
+        assert_eq!((s.bonus_left, s.bonus_cards_left), (1, 1));
+        assert_eq!(Game::from_state(Rules::standard(), s.clone()).state(), s);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn bonus_cards_left() {
+        let rules = Rules {
+            bonus_cards: 2,
+            ..Rules::standard()
+        };
+        let mut s = State {
+            board: board(),
+            deck: vec![Tile::TWO],
+            bonus_left: 10,
+            bonus_cards_left: 1,
+            next: NextHint::Exact(Tile::ONE),
+        };
+        assert_eq!(Game::from_state(rules.clone(), s.clone()).state(), s);
+        s.bonus_cards_left = 3;
+        assert!(Game::try_from_state(rules, s).is_err());
+    }
+
+    #[test]
+    fn hints() {
+        let mut cells = board().cells().to_vec();
+        cells[0] = Tile::new(384);
+        let b = Board::from_tiles(4, 4, &cells);
+        let g = Game::with_board(b, &[2]);
+        let mut g2 = g.clone();
+        let p = g2.shift(Move::Down).unwrap();
+        let hints = p.hints();
+        let bonus: f64 = hints
+            .iter()
+            .filter(|(h, _)| h.is_bonus())
+            .map(|(_, p)| p)
+            .sum();
+        assert_eq!(hints.iter().filter(|(h, _)| h.is_bonus()).count(), 2);
+        assert!(bonus > 0.0 && bonus <= 1.0);
+        assert!(hints.contains(&(NextHint::bonus(&[12, 24, 48].map(Tile::new)), bonus / 2.0)));
+    }
+
+    #[test]
+    fn next_hint() {
+        let h = NextHint::bonus(&[6, 12, 24].map(Tile::new));
+        assert_eq!(
+            (h.to_string(), format!("{:#}", h)),
+            ("+".into(), "+6/12/24".into())
+        );
+        assert_eq!(NextHint::Exact(Tile::TWO).to_string(), "2");
+        assert_eq!(h.probability(Tile::new(12)), 1.0 / 3.0);
+        assert_eq!(h.probability(Tile::THREE), 0.0);
+        assert_eq!(
+            NextHint::Exact(Tile::TWO)
+                .probabilities()
+                .collect::<Vec<_>>(),
+            [(Tile::TWO, 1.0)]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn next_hint_json() {
+        let h = NextHint::bonus(&[6, 12, 24].map(Tile::new));
+        let json = serde_json::to_string(&h).unwrap();
+        assert_eq!(serde_json::from_str::<NextHint>(&json).unwrap(), h);
     }
 }
@@ -0,0 +1,224 @@
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{Error, Game, Move, NextHint},
+    puzzle::{Objective, Puzzle},
+    rules::Rules,
+    tile::Tile,
+};
+
+/// A position where one move is clearly right, found by Generator.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Candidate {
+    /// Survive puzzle.move_limit moves from the position.
+    pub puzzle: Puzzle,
+    pub answer: Move,
+    /// The chance of dying within the move limit after each of Move::ALL,
+    /// playing on as well as possible; None if the move is illegal.
+    pub death: [Option<f64>; 4],
+    /// The lookahead needed to tell the answer apart (1 up to the move
+    /// limit), plus one if the move that leaves the most empty cells is not
+    /// the answer.
+    pub difficulty: u32,
+}
+
+/// Searches games for training positions: ones where only one move avoids
+/// dying within depth moves, or where every other move is at least margin
+/// more likely to die.  Every chance of dying is exact, taken over every
+/// spawn position, value and following preview.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    pub rules: Rules,
+    pub depth: u32,
+    pub margin: f64,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self {
+            rules: Rules::standard(),
+            depth: 2,
+            margin: 0.25,
+        }
+    }
+}
+
+impl Generator {
+    /// Plays a game dealt from seed, choosing moves with policy (e.g. at
+    /// random or with an AI) until it ends or policy returns None, and returns
+    /// the candidate positions met along the way.
+    pub fn play(&self, seed: u64, mut policy: impl FnMut(&Game) -> Option<Move>) -> Vec<Candidate> {
+        let mut g = Game::seeded(self.rules.clone(), Tile::EMPTY, 0, seed);
+        let mut found = Vec::new();
+        while let Some(mv) = policy(&g) {
+            found.extend(self.examine(&g, seed));
+            if g.apply(mv) == Err(Error::GameOver) {
+                break;
+            }
+        }
+        found
+    }
+    /// Returns the position in g as a candidate if it has a clear answer.
+    /// The exported puzzle spawns from seed.
+    pub fn examine(&self, g: &Game, seed: u64) -> Option<Candidate> {
+        let death = death_chances(g, self.depth);
+        let answer = self.answer(&death)?;
+        let needed = (1..self.depth)
+            .find(|&k| self.answer(&death_chances(g, k)) == Some(answer))
+            .unwrap_or(self.depth);
+        Some(Candidate {
+            puzzle: Puzzle {
+                rules: g.rules().clone(),
+                start: g.state(),
+                seed,
+                script: vec![],
+                objective: Objective::Survive,
+                move_limit: self.depth as usize,
+            },
+            answer,
+            death,
+            difficulty: needed + (greedy(g) != Some(answer)) as u32,
+        })
+    }
+    // Returns the only move that clearly beats the others, given each move's
+    // chance of dying.
+    fn answer(&self, death: &[Option<f64>; 4]) -> Option<Move> {
+        let legal: Vec<(Move, f64)> = Move::ALL
+            .into_iter()
+            .zip(death)
+            .filter_map(|(mv, d)| Some((mv, (*d)?)))
+            .collect();
+        let &(best, p) = legal.iter().min_by(|a, b| a.1.total_cmp(&b.1))?;
+        let others = legal.iter().filter(|(mv, _)| *mv != best);
+        let clear = legal.len() > 1
+            && others
+                .clone()
+                .all(|&(_, q)| q >= p + self.margin || (p == 0.0 && q > 0.0));
+        clear.then_some(best)
+    }
+}
+
+/// Checks a candidate against a fresh exhaustive search of its position.
+pub fn verify(c: &Candidate) -> bool {
+    // The search covers every deal, so the seed makes no difference.
+    let g = Game::from_state_seeded(c.puzzle.rules.clone(), c.puzzle.start.clone(), 0);
+    death_chances(&g, c.puzzle.move_limit as u32) == c.death
+}
+
+/// Returns the chance of dying within k moves after each of Move::ALL, with
+/// the best play afterwards, or None for an illegal move.
+pub fn death_chances(g: &Game, k: u32) -> [Option<f64>; 4] {
+    Move::ALL.map(|mv| death_chance(g, mv, k))
+}
+
+fn death_chance(g: &Game, mv: Move, k: u32) -> Option<f64> {
+    let total = outcomes(g, mv)?
+        .into_iter()
+        .map(|(pos, value, next, p)| {
+            let mut g = g.clone();
+            let d = match g.shift(mv).unwrap().resolve_observed(pos, value, next) {
+                Err(Error::GameOver) => 1.0,
+                Err(e) => unreachable!("{:?}", e),
+                Ok(_) if k <= 1 => 0.0,
+                Ok(_) => Move::ALL
+                    .into_iter()
+                    .filter_map(|mv| death_chance(&g, mv, k - 1))
+                    .fold(1.0, f64::min),
+            };
+            p * d
+        })
+        .sum();
+    Some(total)
+}
+
+// Every (position, value, next preview) that can follow mv, with its
+// probability; None if mv is illegal.
+fn outcomes(g: &Game, mv: Move) -> Option<Vec<(usize, Tile, NextHint, f64)>> {
+    let mut g = g.clone();
+    let p = g.shift(mv).ok()?;
+    let hints = p.hints();
+    let outcomes = p
+        .spawns()
+        .into_iter()
+        .flat_map(|s| {
+            hints
+                .iter()
+                .map(move |&(next, q)| (s.position, s.value, next, s.probability * q))
+        })
+        .collect();
+    Some(outcomes)
+}
+
+// The legal move that leaves the most empty cells before the new tile lands.
+fn greedy(g: &Game) -> Option<Move> {
+    let mut g = g.clone();
+    let mut best = None;
+    let mut best_empty = 0;
+    for mv in Move::ALL {
+        if let Ok(p) = g.shift(mv) {
+            let empty = p.game().board().empty_cells().count();
+            if best.is_none() || empty > best_empty {
+                best = Some(mv);
+                best_empty = empty;
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::game::{Game, Move};
+    use crate::generator::{death_chances, verify, Generator};
+
+    #[test]
+    fn forced() {
+        // Left is illegal, and Up or Down leaves a board where the new tile
+        // ends the game wherever it lands.
+        let g = Game::with_board(
+            Board::new([2, 12, 2, 24, 3, 1, 3, 0, 6, 3, 12, 1, 12, 6, 2, 24]),
+            &[3],
+        );
+        let death = death_chances(&g, 1);
+        assert_eq!(death, [Some(1.0), Some(1.0), None, Some(0.0)]);
+
+        let gen = Generator {
+            depth: 1,
+            ..Generator::default()
+        };
+        let c = gen.examine(&g, 0).unwrap();
+        assert_eq!(c.answer, Move::Right);
+        assert_eq!(c.puzzle.move_limit, 1);
+        assert!(verify(&c));
+
+        let gen = Generator {
+            depth: 2,
+            ..Generator::default()
+        };
+        assert_eq!(gen.examine(&g, 0).unwrap().death, death_chances(&g, 2));
+    }
+
+    #[test]
+    fn play() {
+        let gen = Generator::default();
+        let mut moves = 0;
+        let found = gen.play(3, |g| {
+            moves += 1;
+            Move::ALL
+                .into_iter()
+                .cycle()
+                .skip(moves)
+                .take(4)
+                .find(|&mv| g.clone().apply(mv).is_ok())
+        });
+        assert!(!found.is_empty());
+        for c in &found {
+            assert!((1..=3).contains(&c.difficulty));
+            assert!(verify(c));
+        }
+    }
+}
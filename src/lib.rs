@@ -1,5 +1,24 @@
 #![allow(dead_code)] // TODO: remove
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-pub(crate) mod board;
+extern crate alloc;
+
+pub mod adversary;
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod blitz;
+pub mod board;
+pub mod daily;
 pub(crate) mod deck;
+pub mod error;
+pub mod event;
 pub mod game;
+pub mod generator;
+pub mod merge;
+pub mod puzzle;
+pub mod race;
+pub mod replay;
+pub mod rules;
+pub mod spawn;
+pub mod tile;
+pub mod versus;
@@ -0,0 +1,196 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::tile::Tile;
+
+/// Decides which tiles merge when one is pushed into another, and into what.
+pub trait MergeRule {
+    /// Reports whether b, pushed into a, merges with it.
+    fn can_merge(&self, a: Tile, b: Tile) -> bool;
+    /// The tile made by merging b into a; only called if can_merge(a, b).
+    fn merged_value(&self, a: Tile, b: Tile) -> Tile;
+    /// The points a tile is worth at the end of a game.
+    fn tile_score(&self, v: Tile) -> u64;
+    /// Reports whether v is a tile that can appear under this rule.
+    fn is_tile(&self, v: Tile) -> bool {
+        !v.is_empty()
+    }
+    /// Whether tiles slide as far as they can (2048) instead of one cell
+    /// (Threes).
+    fn slides_fully(&self) -> bool {
+        false
+    }
+}
+
+/// The Threes! rule: 1 and 2 make 3, and equal tiles of 3 or more double.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Classic;
+
+impl MergeRule for Classic {
+    fn can_merge(&self, a: Tile, b: Tile) -> bool {
+        match (a, b) {
+            (Tile::ONE, Tile::TWO) | (Tile::TWO, Tile::ONE) => true,
+            (Tile::ONE | Tile::TWO, _) => false,
+            _ => a == b,
+        }
+    }
+    fn merged_value(&self, a: Tile, b: Tile) -> Tile {
+        Tile::new(a.value() + b.value())
+    }
+    fn tile_score(&self, v: Tile) -> u64 {
+        v.score()
+    }
+    fn is_tile(&self, v: Tile) -> bool {
+        !v.is_empty() && v.is_valid()
+    }
+}
+
+/// The 2048 rule: equal tiles double, and tiles slide all the way.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Twenty48;
+
+impl MergeRule for Twenty48 {
+    fn can_merge(&self, a: Tile, b: Tile) -> bool {
+        !a.is_empty() && a == b
+    }
+    fn merged_value(&self, a: Tile, b: Tile) -> Tile {
+        Tile::new(a.value() + b.value())
+    }
+    // The points scored while building the tile out of 2s.
+    fn tile_score(&self, v: Tile) -> u64 {
+        let v = v.value();
+        if v < 4 {
+            return 0;
+        }
+        v as u64 * (v.ilog2() as u64 - 1)
+    }
+    fn is_tile(&self, v: Tile) -> bool {
+        v.value() >= 2 && v.value().is_power_of_two()
+    }
+    fn slides_fully(&self) -> bool {
+        true
+    }
+}
+
+/// Fibonacci Threes: neighbouring Fibonacci numbers (1 and 1, 1 and 2, 2 and
+/// 3, 3 and 5, ...) make the next one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fibonacci;
+
+impl Fibonacci {
+    // Returns the position of v in 1, 2, 3, 5, 8, ..., or None if v is not a
+    // Fibonacci number.
+    fn index(v: u32) -> Option<u32> {
+        let (mut a, mut b, mut i) = (1u32, 2u32, 0);
+        while a < v {
+            (a, b) = (b, a.checked_add(b)?);
+            i += 1;
+        }
+        (a == v).then_some(i)
+    }
+}
+
+impl MergeRule for Fibonacci {
+    fn can_merge(&self, a: Tile, b: Tile) -> bool {
+        match (Self::index(a.value()), Self::index(b.value())) {
+            (Some(i), Some(j)) => i.abs_diff(j) == 1 || a == 1 && b == 1,
+            _ => false,
+        }
+    }
+    fn merged_value(&self, a: Tile, b: Tile) -> Tile {
+        Tile::new(a.value() + b.value())
+    }
+    // Like Threes: 3 is worth 3 points, and every step up triples that.
+    fn tile_score(&self, v: Tile) -> u64 {
+        match Self::index(v.value()) {
+            Some(i) if i >= 2 => 3u64.pow(i - 1),
+            _ => 0,
+        }
+    }
+    fn is_tile(&self, v: Tile) -> bool {
+        Self::index(v.value()).is_some()
+    }
+}
+
+/// Selects one of the built-in merge rules, e.g. in Rules.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Merge {
+    #[default]
+    Classic,
+    Twenty48,
+    Fibonacci,
+}
+
+impl MergeRule for Merge {
+    fn can_merge(&self, a: Tile, b: Tile) -> bool {
+        match self {
+            Merge::Classic => Classic.can_merge(a, b),
+            Merge::Twenty48 => Twenty48.can_merge(a, b),
+            Merge::Fibonacci => Fibonacci.can_merge(a, b),
+        }
+    }
+    fn merged_value(&self, a: Tile, b: Tile) -> Tile {
+        match self {
+            Merge::Classic => Classic.merged_value(a, b),
+            Merge::Twenty48 => Twenty48.merged_value(a, b),
+            Merge::Fibonacci => Fibonacci.merged_value(a, b),
+        }
+    }
+    fn tile_score(&self, v: Tile) -> u64 {
+        match self {
+            Merge::Classic => Classic.tile_score(v),
+            Merge::Twenty48 => Twenty48.tile_score(v),
+            Merge::Fibonacci => Fibonacci.tile_score(v),
+        }
+    }
+    fn is_tile(&self, v: Tile) -> bool {
+        match self {
+            Merge::Classic => Classic.is_tile(v),
+            Merge::Twenty48 => Twenty48.is_tile(v),
+            Merge::Fibonacci => Fibonacci.is_tile(v),
+        }
+    }
+    fn slides_fully(&self) -> bool {
+        match self {
+            Merge::Classic => Classic.slides_fully(),
+            Merge::Twenty48 => Twenty48.slides_fully(),
+            Merge::Fibonacci => Fibonacci.slides_fully(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{Classic, Fibonacci, MergeRule, Twenty48};
+    use crate::tile::Tile;
+
+    #[test]
+    fn fibonacci() {
+        let f = Fibonacci;
+        let t = Tile::new;
+        for (a, b) in [(1, 1), (1, 2), (2, 1), (2, 3), (5, 3), (5, 8), (89, 144)] {
+            assert!(f.can_merge(t(a), t(b)), "{a} {b}");
+        }
+        for (a, b) in [(0, 1), (2, 2), (3, 3), (1, 3), (3, 8), (4, 5), (6, 7)] {
+            assert!(!f.can_merge(t(a), t(b)), "{a} {b}");
+        }
+        assert_eq!(f.merged_value(t(3), t(5)), 8);
+        assert_eq!(
+            [1, 2, 3, 5, 8, 13].map(|v| f.tile_score(t(v))),
+            [0, 0, 3, 9, 27, 81]
+        );
+    }
+
+    #[test]
+    fn scores() {
+        assert_eq!(
+            [1, 2, 3, 6, 12, 768].map(|v| Classic.tile_score(Tile::new(v))),
+            [0, 0, 3, 9, 27, 19683]
+        );
+        assert_eq!(
+            [2, 4, 8, 2048].map(|v| Twenty48.tile_score(Tile::new(v))),
+            [0, 4, 16, 20480]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Error, Game, Move, NextHint, State};
+use crate::merge::MergeRule;
+use crate::rules::Rules;
+use crate::tile::Tile;
+
+/// What a puzzle asks the player to do within its move limit.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Objective {
+    /// Make a tile of at least this value.
+    Reach(Tile),
+    /// Get the board's score to at least this.
+    Score(u64),
+    /// Play the whole move limit without the game ending.
+    Survive,
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Objective::Reach(v) => write!(f, "reach {}", v),
+            Objective::Score(s) => write!(f, "score {}", s),
+            Objective::Survive => write!(f, "survive"),
+        }
+    }
+}
+
+/// A spawn a puzzle forces after a given move: the tile lands at position
+/// with value (one of the preview's values), and next becomes the preview,
+/// or is drawn as usual if None.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ScriptedSpawn {
+    pub position: usize,
+    pub value: Tile,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub next: Option<NextHint>,
+}
+
+/// A shareable puzzle: a starting position, how its tiles spawn, and an
+/// objective to meet within move_limit moves.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Puzzle {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rules: Rules,
+    pub start: State,
+    // Seeds the rng that draws every spawn the script does not cover.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seed: u64,
+    // The spawn after each move, in order.  An entry whose position the move
+    // did not vacate (the player went another way) is skipped in favour of a
+    // random spawn, as are the moves after the script runs out.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub script: Vec<ScriptedSpawn>,
+    pub objective: Objective,
+    pub move_limit: usize,
+}
+
+impl Puzzle {
+    /// Checks that the start could occur under the rules (see
+    /// State::validate) and that every scripted spawn is a tile on the board.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.start.validate(&self.rules)?;
+        let cells = self.rules.width * self.rules.height;
+        for s in &self.script {
+            if s.position >= cells {
+                return Err(Error::InvalidPosition(s.position));
+            }
+            if !self.rules.merge.is_tile(s.value) {
+                return Err(Error::InvalidTile(s.value.value()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Status {
+    Playing,
+    Solved,
+    Failed,
+}
+
+/// A game of a puzzle, tracking the moves made and whether the objective has
+/// been met.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PuzzleGame {
+    puzzle: Puzzle,
+    g: Game,
+    moves: usize,
+    status: Status,
+}
+
+impl PuzzleGame {
+    pub fn new(puzzle: Puzzle) -> Self {
+        let g = Game::from_state_seeded(puzzle.rules.clone(), puzzle.start.clone(), puzzle.seed);
+        let mut s = Self {
+            puzzle,
+            g,
+            moves: 0,
+            status: Status::Playing,
+        };
+        s.update(s.g.can_move());
+        s
+    }
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+    pub fn game(&self) -> &Game {
+        &self.g
+    }
+    pub fn status(&self) -> Status {
+        self.status
+    }
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+    pub fn moves_left(&self) -> usize {
+        self.puzzle.move_limit.saturating_sub(self.moves)
+    }
+    /// Plays mv and returns the puzzle's status afterwards.  Returns
+    /// IllegalMove if nothing moved, GameOver if the puzzle is already solved
+    /// or failed, or IllegalSpawn (undoing the move) if the script's next
+    /// could not have been drawn.
+    pub fn apply(&mut self, mv: Move) -> Result<Status, Error> {
+        if self.status != Status::Playing {
+            return Err(Error::GameOver);
+        }
+        let script = self.puzzle.script.get(self.moves).copied();
+        let p = self.g.shift(mv)?;
+        let res = match script {
+            Some(s) if p.open().contains(&s.position) => match s.next {
+                Some(next) => p.resolve_observed(s.position, s.value, next),
+                None => p.resolve(s.position, s.value),
+            },
+            _ => p.resolve_random(),
+        };
+        if let Err(e @ (Error::IllegalMove | Error::IllegalSpawn)) = res {
+            return Err(e);
+        }
+        self.moves += 1;
+        self.update(res.is_ok());
+        Ok(self.status)
+    }
+    // Sets the status from the current position; alive is whether the game
+    // can go on.
+    fn update(&mut self, alive: bool) {
+        let met = match self.puzzle.objective {
+            Objective::Reach(v) => self.g.board().cells().iter().any(|&c| c >= v),
+            Objective::Score(s) => self.g.score() >= s,
+            Objective::Survive => alive && self.moves >= self.puzzle.move_limit,
+        };
+        self.status = if met {
+            Status::Solved
+        } else if !alive || self.moves >= self.puzzle.move_limit {
+            Status::Failed
+        } else {
+            Status::Playing
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::game::{Error, Move, NextHint, State};
+    use crate::puzzle::{Objective, Puzzle, PuzzleGame, ScriptedSpawn, Status};
+    use crate::rules::Rules;
+    use crate::tile::Tile;
+
+    fn puzzle(objective: Objective, move_limit: usize) -> Puzzle {
+        Puzzle {
+            rules: Rules::standard(),
+            start: State {
+                board: Board::new([3, 3, 0, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                deck: vec![Tile::ONE, Tile::TWO, Tile::THREE],
+                bonus_left: 10,
+                bonus_cards_left: 0,
+                next: NextHint::Exact(Tile::THREE),
+            },
+            seed: 7,
+            script: vec![ScriptedSpawn {
+                position: 3,
+                value: Tile::THREE,
+                next: Some(NextHint::Exact(Tile::ONE)),
+            }],
+            objective,
+            move_limit,
+        }
+    }
+
+    #[test]
+    fn reach() {
+        let mut p = PuzzleGame::new(puzzle(Objective::Reach(Tile::new(6)), 1));
+        assert_eq!(p.status(), Status::Playing);
+        assert_eq!(p.apply(Move::Up), Err(Error::IllegalMove));
+        assert_eq!(p.apply(Move::Left), Ok(Status::Solved));
+        assert_eq!(p.game().board().cells()[..4], [6, 0, 2, 3]);
+        assert_eq!(p.game().next(), NextHint::Exact(Tile::ONE));
+        assert_eq!(p.apply(Move::Right), Err(Error::GameOver));
+    }
+
+    #[test]
+    fn move_limit() {
+        let mut p = PuzzleGame::new(puzzle(Objective::Reach(Tile::new(12)), 2));
+        assert_eq!(p.apply(Move::Left), Ok(Status::Playing));
+        assert_eq!(p.moves_left(), 1);
+        assert_eq!(p.apply(Move::Down), Ok(Status::Failed));
+
+        let mut p = PuzzleGame::new(puzzle(Objective::Survive, 2));
+        assert_eq!(p.apply(Move::Right), Ok(Status::Playing));
+        assert_eq!(p.apply(Move::Down), Ok(Status::Solved));
+    }
+
+    #[test]
+    fn seeded() {
+        // Right does not vacate the scripted position, so both spawns are
+        // drawn from the seed.
+        let mut a = PuzzleGame::new(puzzle(Objective::Survive, 5));
+        let mut b = a.clone();
+        for mv in [Move::Right, Move::Down] {
+            assert_eq!(a.apply(mv), b.apply(mv));
+        }
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn checked_when_read() {
+        let json = serde_json::to_string(&puzzle(Objective::Survive, 5)).unwrap();
+        let mut p: Puzzle = serde_json::from_str(&json).unwrap();
+        assert_eq!(p, puzzle(Objective::Survive, 5));
+        assert_eq!(p.validate(), Ok(()));
+        p.script[0].position = 16;
+        assert_eq!(p.validate(), Err(Error::InvalidPosition(16)));
+    }
+}
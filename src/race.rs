@@ -0,0 +1,203 @@
+use alloc::{vec, vec::Vec};
+use rand::prelude::*;
+use rand_pcg::Pcg32;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{Error, Game, Move, NextHint},
+    rules::Rules,
+    tile::Tile,
+};
+
+/// How a player is doing in a race.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Progress {
+    pub moves: usize,
+    pub score: u64,
+    pub max: Tile,
+    /// The player's place (from 1) once they have made the target tile.
+    pub place: Option<usize>,
+    /// Whether the player's game ended before they made the target tile.
+    pub out: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Racer {
+    g: Game,
+    // Places the new tiles, so that the game's own rng only draws cards.
+    placement: Pcg32,
+    moves: usize,
+    out: bool,
+}
+
+/// Several players racing on the same game: every player's deck comes from
+/// the same seed and is drawn only by their own moves, so they all see the
+/// same cards in the same order however the others play.  The first to make
+/// a tile of at least target wins; the others keep going for the remaining
+/// places.
+#[derive(Clone, Debug)]
+pub struct Race {
+    racers: Vec<Racer>,
+    target: Tile,
+    finished: Vec<usize>,
+}
+
+impl Race {
+    /// Starts players identical games under rules, dealt from seed with a
+    /// boost tile (or Tile::EMPTY for none) at boostpos.
+    pub fn new(
+        rules: Rules,
+        boost: Tile,
+        boostpos: usize,
+        seed: u64,
+        players: usize,
+        target: Tile,
+    ) -> Self {
+        let g = Game::seeded(rules, boost, boostpos, seed);
+        let racer = Racer {
+            g,
+            placement: Pcg32::new(seed, 0xa02bdbf7bb3c0a7),
+            moves: 0,
+            out: false,
+        };
+        Self {
+            racers: vec![racer; players],
+            target,
+            finished: Vec::new(),
+        }
+    }
+    pub fn players(&self) -> usize {
+        self.racers.len()
+    }
+    pub fn target(&self) -> Tile {
+        self.target
+    }
+    pub fn game(&self, player: usize) -> &Game {
+        &self.racers[player].g
+    }
+    pub fn progress(&self, player: usize) -> Progress {
+        let r = &self.racers[player];
+        Progress {
+            moves: r.moves,
+            score: r.g.score(),
+            max: r
+                .g
+                .board()
+                .cells()
+                .iter()
+                .copied()
+                .max()
+                .unwrap_or(Tile::EMPTY),
+            place: self
+                .finished
+                .iter()
+                .position(|&p| p == player)
+                .map(|i| i + 1),
+            out: r.out,
+        }
+    }
+    /// The players who have made the target tile, in the order they did.
+    pub fn finish_order(&self) -> &[usize] {
+        &self.finished
+    }
+    /// Every player from first to last: those who made the target in order,
+    /// then the rest by score.
+    pub fn standings(&self) -> Vec<usize> {
+        let mut rest: Vec<usize> = (0..self.players())
+            .filter(|p| !self.finished.contains(p))
+            .collect();
+        rest.sort_by_key(|&p| core::cmp::Reverse(self.racers[p].g.score()));
+        self.finished.iter().copied().chain(rest).collect()
+    }
+    /// Reports whether every player has either finished or is out.
+    pub fn is_over(&self) -> bool {
+        (0..self.players()).all(|p| self.racers[p].out || self.finished.contains(&p))
+    }
+    /// Plays mv for player.  Returns GameOver if the player has already
+    /// finished or is out, or if this move ends their game.
+    pub fn apply(&mut self, player: usize, mv: Move) -> Result<NextHint, Error> {
+        if self.racers[player].out || self.finished.contains(&player) {
+            return Err(Error::GameOver);
+        }
+        let r = &mut self.racers[player];
+        let p = r.g.shift(mv)?;
+        let pos = *p.open().choose(&mut r.placement).unwrap();
+        let value = *p.candidates().values().choose(&mut r.placement).unwrap();
+        let res = p.resolve(pos, value);
+        r.moves += 1;
+        r.out = res == Err(Error::GameOver);
+        if r.g.board().cells().iter().any(|&c| c >= self.target) {
+            r.out = false;
+            self.finished.push(player);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Error, Move};
+    use crate::race::Race;
+    use crate::rules::Rules;
+    use crate::tile::Tile;
+
+    #[test]
+    fn same_cards() {
+        // Frequent bonus tiles, with a choice of set that grows with every
+        // doubling of the largest tile.
+        let rules = Rules {
+            bonus_deck_len: 6,
+            bonus_cards: 2,
+            bonus_unlock: 3,
+            bonus_min: 3,
+            bonus_divisor: 1,
+            bonus_window: 1,
+            ..Rules::standard()
+        };
+        let mut race = Race::new(rules, Tile::EMPTY, 0, 11, 2, Tile::three_times_pow2(16));
+        let mut seen = [vec![], vec![]];
+        for (p, order) in [
+            (0, Move::ALL),
+            (1, [Move::Right, Move::Down, Move::Left, Move::Up]),
+        ] {
+            for mv in order.iter().cycle().take(200) {
+                if seen[p].len() == 30 {
+                    break;
+                }
+                let next = race.game(p).next();
+                if race.apply(p, *mv) != Err(Error::IllegalMove) {
+                    // Only the bonus tiles depend on the player's board.
+                    seen[p].push(if next.is_bonus() { None } else { Some(next) });
+                }
+            }
+        }
+        assert_eq!(seen[0].len(), 30);
+        assert!(seen[0].contains(&None));
+        assert_eq!(seen[0], seen[1]);
+        assert_ne!(race.game(0).board(), race.game(1).board());
+    }
+
+    #[test]
+    fn finish_order() {
+        let mut race = Race::new(Rules::standard(), Tile::EMPTY, 0, 5, 3, Tile::new(12));
+        let mut turn = 0;
+        while !race.is_over() && turn < 10000 {
+            turn += 1;
+            for p in [1, 2, 0] {
+                let _ = race.apply(p, Move::ALL[(turn + p) % 4]);
+            }
+        }
+        assert!(race.is_over());
+        let order = race.finish_order().to_vec();
+        for (i, &p) in order.iter().enumerate() {
+            assert_eq!(race.progress(p).place, Some(i + 1));
+            assert!(race.progress(p).max >= 12);
+            assert_eq!(race.apply(p, Move::Up), Err(Error::GameOver));
+        }
+        let standings = race.standings();
+        assert_eq!(standings[..order.len()], order[..]);
+        assert_eq!(standings.len(), 3);
+    }
+}
@@ -0,0 +1,107 @@
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Error, Game, Move};
+
+/// A recorded game: the starting position (including its rng) and every move
+/// that was played from it.  Since the rng travels with the game, replaying
+/// the moves reproduces every spawn exactly.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Replay {
+    start: Game,
+    moves: Vec<Move>,
+}
+
+impl Replay {
+    pub fn new(start: Game) -> Self {
+        Self {
+            start,
+            moves: Vec::new(),
+        }
+    }
+    pub fn start(&self) -> &Game {
+        &self.start
+    }
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+    // Records a move.  Only moves that changed the board (including the final
+    // one that ended the game) should be recorded.
+    pub fn push(&mut self, mv: Move) {
+        self.moves.push(mv);
+    }
+    // Drops every move after the first n, e.g. to follow an undo.
+    pub fn truncate(&mut self, n: usize) {
+        self.moves.truncate(n);
+    }
+    /// Iterates over the recorded moves along with the game as it was just
+    /// before each one was played.
+    pub fn iter(&self) -> impl Iterator<Item = (Game, Move)> + '_ {
+        let mut g = self.start.clone();
+        self.moves.iter().map(move |&mv| {
+            let before = g.clone();
+            let _ = g.apply(mv);
+            (before, mv)
+        })
+    }
+    /// Returns the game after all recorded moves, or IllegalMove if the replay
+    /// does not match its starting position.
+    pub fn end(&self) -> Result<Game, Error> {
+        let mut g = self.start.clone();
+        for &mv in &self.moves {
+            // A move after the game ended does not match either.
+            if !g.can_move() || g.apply(mv) == Err(Error::IllegalMove) {
+                return Err(Error::IllegalMove);
+            }
+        }
+        Ok(g)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::game::{Error, Game, Move};
+    use crate::replay::Replay;
+    use crate::tile::Tile;
+
+    #[test]
+    fn replays_exactly() {
+        let mut g = Game::new(Tile::new(192), 12);
+        let mut r = Replay::new(g.clone());
+        for mv in Move::ALL.iter().cycle().take(40) {
+            if !g.can_move() {
+                break;
+            }
+            if g.apply(*mv) != Err(Error::IllegalMove) {
+                r.push(*mv);
+            }
+        }
+        assert_eq!(r.end(), Ok(g));
+
+        let seen: Vec<Move> = r.iter().map(|(_, mv)| mv).collect();
+        assert_eq!(seen, r.moves());
+        let (first, _) = r.iter().next().unwrap();
+        assert_eq!(&first, r.start());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn checked_when_read() {
+        use serde_json::json;
+
+        let r = Replay::new(Game::new(Tile::EMPTY, 0));
+        let json = serde_json::to_value(&r).unwrap();
+        assert_eq!(serde_json::from_value::<Replay>(json.clone()).unwrap(), r);
+        for (field, bad) in [("deck", json!([])), ("bonus_window", json!(4))] {
+            let mut json = json.clone();
+            json["start"]["rules"][field] = bad;
+            assert!(serde_json::from_value::<Replay>(json).is_err());
+        }
+        let mut json = json.clone();
+        json["start"]["next"] = json!({"Exact": 5});
+        let e = serde_json::from_value::<Game>(json["start"].take()).unwrap_err();
+        assert_eq!(e.to_string(), "5 is not a card in the deck");
+    }
+}
@@ -0,0 +1,227 @@
+use alloc::{format, string::String, vec, vec::Vec};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::valid_size,
+    error::Error,
+    game::Candidates,
+    merge::{Merge, MergeRule},
+    tile::Tile,
+};
+
+/// The tunable rules of a game: what the deck holds and when and how bonus
+/// tiles appear.  Rules::default() is the standard game.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rules {
+    /// The board width; see board::MAX_SIZE.
+    pub width: usize,
+    /// The board height; see board::MAX_SIZE.
+    pub height: usize,
+    /// Which tiles merge, and how far tiles slide.
+    pub merge: Merge,
+    /// Whether new tiles may land on any empty cell instead of only the cells
+    /// vacated by the move.
+    pub spawn_anywhere: bool,
+    /// The cards in every freshly shuffled deck.  Must not be empty.
+    pub deck: Vec<Tile>,
+    /// How the deck's cards are dealt.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dealing: Dealing,
+    /// The number of tiles dealt onto the board when a game starts.
+    pub initial_tiles: usize,
+    /// The number of cards in the bonus deck, which is drawn alongside the
+    /// deck; when one of its bonus cards comes up (and bonus tiles are
+    /// unlocked) a bonus tile replaces the deck card.
+    pub bonus_deck_len: usize,
+    /// The number of bonus cards in the bonus deck.
+    pub bonus_cards: usize,
+    /// Bonus tiles are unlocked once the largest tile reaches bonus_unlock.
+    pub bonus_unlock: u32,
+    /// The smallest bonus tile.  Bonus tiles are bonus_min * 2^k, up to the
+    /// largest tile divided by bonus_divisor.
+    pub bonus_min: u32,
+    /// See bonus_min.
+    pub bonus_divisor: u32,
+    /// When more than bonus_window (at most 3) values qualify for a bonus
+    /// tile, a random run of bonus_window consecutive ones is offered.
+    pub bonus_window: usize,
+}
+
+/// How new tiles are dealt from Rules::deck.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Dealing {
+    /// In the order of a shuffled deck, with a fresh one once it runs out,
+    /// alongside the bonus deck (spawn::DeckTiles).
+    #[default]
+    Shuffled,
+    /// Independently each time, with chances proportional to how often each
+    /// card appears in the deck (spawn::WeightedTiles), and no bonus tiles.
+    Independent,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl Rules {
+    /// The rules of the original game.
+    pub fn standard() -> Self {
+        Self {
+            width: 4,
+            height: 4,
+            merge: Merge::Classic,
+            spawn_anywhere: false,
+            deck: [[Tile::ONE; 4], [Tile::TWO; 4], [Tile::THREE; 4]].concat(),
+            dealing: Dealing::Shuffled,
+            initial_tiles: 8,
+            bonus_deck_len: 21,
+            bonus_cards: 1,
+            bonus_unlock: 48,
+            bonus_min: 6,
+            bonus_divisor: 8,
+            bonus_window: 3,
+        }
+    }
+    /// The standard game without bonus tiles.
+    pub fn no_bonus() -> Self {
+        Self {
+            bonus_cards: 0,
+            ..Self::standard()
+        }
+    }
+    /// 2048 on the same engine: equal tiles double and slide all the way, and a
+    /// 2 (or one time in ten a 4) lands on any empty cell.
+    pub fn twenty48() -> Self {
+        let mut deck = vec![Tile::new(2); 9];
+        deck.push(Tile::new(4));
+        Self {
+            merge: Merge::Twenty48,
+            spawn_anywhere: true,
+            deck,
+            dealing: Dealing::Independent,
+            initial_tiles: 2,
+            bonus_cards: 0,
+            ..Self::standard()
+        }
+    }
+    /// Fibonacci Threes: neighbouring Fibonacci numbers merge, starting from a
+    /// deck of 1s and 2s, without bonus tiles.
+    pub fn fibonacci() -> Self {
+        Self {
+            merge: Merge::Fibonacci,
+            deck: [vec![Tile::ONE; 8], vec![Tile::TWO; 4]].concat(),
+            bonus_cards: 0,
+            ..Self::standard()
+        }
+    }
+    /// Returns InvalidRules if a game cannot be played under the rules: the
+    /// board size is unsupported, the deck is empty or holds something that
+    /// is not a tile, or bonus tiles would have more than three candidates.
+    pub fn validate(&self) -> Result<(), Error> {
+        let bad = |msg: String| Err(Error::InvalidRules(msg));
+        if !valid_size(self.width, self.height) {
+            return bad(format!(
+                "a {}x{} board is not supported",
+                self.width, self.height
+            ));
+        }
+        if self.deck.is_empty() {
+            return bad("the deck is empty".into());
+        }
+        if let Some(c) = self.deck.iter().find(|&&c| !self.merge.is_tile(c)) {
+            return bad(format!("the deck holds {}, which is not a tile", c.value()));
+        }
+        if self.bonus_cards > self.bonus_deck_len {
+            return bad(format!(
+                "{} bonus cards do not fit in a bonus deck of {}",
+                self.bonus_cards, self.bonus_deck_len
+            ));
+        }
+        if self.bonus_window > 3 {
+            return bad(format!(
+                "a bonus tile has at most 3 candidates, not {}",
+                self.bonus_window
+            ));
+        }
+        Ok(())
+    }
+    /// Returns the possible sets of bonus tiles for a board whose largest tile
+    /// is max; one of them is picked at random when a bonus card comes up.
+    pub fn bonus_sets(&self, max: Tile) -> Vec<Vec<Tile>> {
+        (0..self.bonus_set_count(max))
+            .filter_map(|i| self.bonus_set(max, i))
+            .map(|c| c.to_vec())
+            .collect()
+    }
+    /// The number of sets bonus_sets(max) returns, without building them.
+    pub fn bonus_set_count(&self, max: Tile) -> usize {
+        match self.bonus_values(max) {
+            0 => 0,
+            n if n <= self.bonus_window => 1,
+            n => n - self.bonus_window.max(1) + 1,
+        }
+    }
+    /// Set i of bonus_sets(max), without allocating, or None if there is no
+    /// such set.
+    pub fn bonus_set(&self, max: Tile, i: usize) -> Option<Candidates> {
+        if i >= self.bonus_set_count(max) {
+            return None;
+        }
+        let len = self.bonus_values(max).min(self.bonus_window.max(1));
+        let mut vals = [Tile::EMPTY; 3];
+        for (j, v) in vals[..len].iter_mut().enumerate() {
+            *v = Tile::new(self.bonus_min << (i + j));
+        }
+        Some(Candidates::new(&vals[..len]))
+    }
+    // Returns how many bonus values there are for a board whose largest tile
+    // is max: bonus_min, 2 * bonus_min, ... up to max / bonus_divisor.
+    fn bonus_values(&self, max: Tile) -> usize {
+        if max < self.bonus_unlock || self.bonus_min == 0 {
+            return 0;
+        }
+        let top = max.value() / self.bonus_divisor.max(1);
+        core::iter::successors(Some(self.bonus_min), |v| v.checked_mul(2))
+            .take_while(|&v| v <= top)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::Rules;
+    use crate::tile::Tile;
+
+    #[test]
+    fn bonus_sets() {
+        let r = Rules::standard();
+        for m in [0, 1, 2, 3, 6, 12, 24] {
+            assert!(r.bonus_sets(Tile::new(m)).is_empty());
+        }
+        assert_eq!(r.bonus_sets(Tile::new(48)), vec![vec![6]]);
+        assert_eq!(r.bonus_sets(Tile::new(96)), vec![vec![6, 12]]);
+        assert_eq!(r.bonus_sets(Tile::new(192)), vec![vec![6, 12, 24]]);
+        assert_eq!(
+            r.bonus_sets(Tile::new(384)),
+            vec![vec![6, 12, 24], vec![12, 24, 48]]
+        );
+        assert_eq!(r.bonus_sets(Tile::new(1536)).len(), 4);
+        assert_eq!(r.bonus_sets(Tile::new(1536))[3], vec![48, 96, 192]);
+
+        let r = Rules {
+            bonus_unlock: 192,
+            bonus_window: 2,
+            ..Rules::standard()
+        };
+        assert!(r.bonus_sets(Tile::new(96)).is_empty());
+        assert_eq!(
+            r.bonus_sets(Tile::new(192)),
+            vec![vec![6, 12], vec![12, 24]]
+        );
+    }
+}
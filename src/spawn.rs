@@ -0,0 +1,813 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{any::Any, fmt::Debug};
+use rand::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    deck::Deck,
+    error::Error,
+    game::{NextHint, State},
+    rules::{Dealing, Rules},
+    tile::Tile,
+};
+
+/// Decides which tile comes next.  max is the largest tile on the board at the
+/// time of the draw, which limits the bonus tiles.
+pub trait TileSource {
+    /// Draws the hint for the tile after the current one.
+    fn draw(&mut self, rules: &Rules, max: Tile, rng: &mut dyn RngCore) -> NextHint;
+    /// Every hint draw may return, with its probability.
+    fn hints(&self, rules: &Rules, max: Tile) -> Vec<(NextHint, f64)>;
+    /// Updates the source as if draw had returned next.  Returns false,
+    /// changing nothing, if draw could not have returned it.
+    fn observe(&mut self, rules: &Rules, max: Tile, next: NextHint, rng: &mut dyn RngCore) -> bool;
+    /// Reshuffles whatever the player cannot see; see Game::rerand.
+    fn shuffle(&mut self, _rng: &mut dyn RngCore) {}
+}
+
+/// Decides where a new tile lands.
+pub trait SpawnPlacement {
+    /// Picks one of the open positions, of which there is at least one.
+    fn place(&mut self, open: &[usize], rng: &mut dyn RngCore) -> usize;
+    /// Every position place may pick, with its probability.  By default all
+    /// the open positions are equally likely.
+    fn probabilities(&self, open: &[usize]) -> Vec<(usize, f64)> {
+        let p = 1.0 / open.len() as f64;
+        open.iter().map(|&pos| (pos, p)).collect()
+    }
+}
+
+/// The Threes! dealer: cards come from a shuffled deck, and now and then a
+/// bonus tile comes from the bonus deck instead.
+#[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeckTiles {
+    deck: Deck,
+    // true for a bonus card; drawn from alongside the deck.
+    bonus: Vec<bool>,
+}
+
+impl DeckTiles {
+    /// A freshly shuffled deck and bonus deck.  If boosted, the first bonus
+    /// deck has no bonus cards, as in a game started with a boost tile.
+    pub fn new(rules: &Rules, boosted: bool, rng: &mut dyn RngCore) -> Self {
+        let mut t = Self {
+            deck: Deck::new(&rules.deck, rng),
+            bonus: vec![false; rules.bonus_deck_len],
+        };
+        if !boosted {
+            t.new_bonus_deck(rules, rng);
+        }
+        t
+    }
+    /// The deck and bonus deck of an observed state, with the unknowns
+    /// shuffled.
+    pub fn from_state(rules: &Rules, s: &State, rng: &mut dyn RngCore) -> Self {
+        let mut bonus = vec![false; s.bonus_left];
+        let n = s.bonus_cards_left.min(rules.bonus_cards).min(s.bonus_left);
+        bonus[..n].fill(true);
+        bonus.shuffle(rng);
+        Self {
+            deck: Deck::from_cards(s.deck.clone(), rng),
+            bonus,
+        }
+    }
+    /// The cards left in the deck, sorted.
+    pub fn deck(&self) -> Vec<Tile> {
+        let mut d = self.deck.contents.clone();
+        d.sort();
+        d
+    }
+    /// The number of cards left in the bonus deck.
+    pub fn bonus_left(&self) -> usize {
+        self.bonus.len()
+    }
+    /// The number of bonus cards left in the bonus deck.
+    pub fn bonus_cards_left(&self) -> usize {
+        self.bonus.iter().filter(|&&b| b).count()
+    }
+    // Reports whether the next card starts a fresh deck.
+    pub(crate) fn deck_empty(&self) -> bool {
+        self.deck.contents.is_empty()
+    }
+    // Draws a card from the deck alone, as for the initial tiles.
+    pub(crate) fn card(&mut self, rules: &Rules, rng: &mut dyn RngCore) -> Tile {
+        self.deck.next(&rules.deck, rng)
+    }
+    fn new_bonus_deck(&mut self, rules: &Rules, rng: &mut dyn RngCore) {
+        // Refill in place, keeping the allocation.
+        let len = rules.bonus_deck_len;
+        self.bonus.clear();
+        self.bonus.resize(len, false);
+        self.bonus[..rules.bonus_cards.min(len)].fill(true);
+        self.bonus.shuffle(rng);
+    }
+    fn draw_bonus(&mut self, rules: &Rules, max: Tile, rng: &mut dyn RngCore) -> Option<NextHint> {
+        if self.bonus.is_empty() {
+            self.new_bonus_deck(rules, rng);
+        }
+        if !self.bonus.pop()? {
+            return None;
+        }
+        // Always take exactly one number for the choice of set, however many
+        // sets max allows, so that games dealt from the same seed (as in a
+        // race) stay in step whatever tiles their players have made.
+        let pick = rng.next_u32() as u64;
+        let n = rules.bonus_set_count(max);
+        let candidates = rules.bonus_set(max, ((pick * n as u64) >> 32) as usize)?;
+        Some(NextHint::Bonus { candidates })
+    }
+}
+
+// Like Deck, a clone keeps the bonus deck's capacity.
+impl Clone for DeckTiles {
+    fn clone(&self) -> Self {
+        let mut bonus = Vec::with_capacity(self.bonus.capacity());
+        bonus.extend_from_slice(&self.bonus);
+        Self {
+            deck: self.deck.clone(),
+            bonus,
+        }
+    }
+}
+
+impl TileSource for DeckTiles {
+    fn draw(&mut self, rules: &Rules, max: Tile, rng: &mut dyn RngCore) -> NextHint {
+        self.draw_bonus(rules, max, rng)
+            .unwrap_or_else(|| NextHint::Exact(self.deck.next(&rules.deck, rng)))
+    }
+    fn hints(&self, rules: &Rules, max: Tile) -> Vec<(NextHint, f64)> {
+        let mut hints = Vec::new();
+        let (bonus, len) = if self.bonus.is_empty() {
+            let len = rules.bonus_deck_len;
+            (rules.bonus_cards.min(len), len)
+        } else {
+            (self.bonus.iter().filter(|&&x| x).count(), self.bonus.len())
+        };
+        let mut p_deck = 1.0;
+        let sets = rules.bonus_sets(max);
+        if bonus > 0 && !sets.is_empty() {
+            let p_bonus = bonus as f64 / len as f64;
+            p_deck -= p_bonus;
+            for set in &sets {
+                hints.push((NextHint::bonus(set), p_bonus / sets.len() as f64));
+            }
+        }
+        let counts = if self.deck.contents.is_empty() {
+            Deck {
+                contents: rules.deck.clone(),
+            }
+            .counts()
+        } else {
+            self.deck.counts()
+        };
+        let total: u32 = counts.iter().map(|c| c.1).sum();
+        for (c, n) in counts {
+            hints.push((NextHint::Exact(c), p_deck * n as f64 / total as f64));
+        }
+        hints
+    }
+    // Consumes the deck and bonus deck cards that must have produced next.
+    fn observe(&mut self, rules: &Rules, max: Tile, next: NextHint, rng: &mut dyn RngCore) -> bool {
+        // Whether the bonus deck (a fresh one, if it is empty) holds a plain
+        // card and a bonus card.
+        let (plain, bonus) = if self.bonus.is_empty() {
+            let n = rules.bonus_cards.min(rules.bonus_deck_len);
+            (rules.bonus_deck_len > n, n > 0)
+        } else {
+            (self.bonus.contains(&false), self.bonus.contains(&true))
+        };
+        let unlocked = rules.bonus_set_count(max) > 0;
+        let possible = match next {
+            // Until bonus tiles are unlocked, a bonus card deals a deck card.
+            NextHint::Exact(_) if bonus && !plain && unlocked => false,
+            NextHint::Exact(c) if self.deck.contents.is_empty() => rules.deck.contains(&c),
+            NextHint::Exact(c) => self.deck.contents.contains(&c),
+            NextHint::Bonus { candidates } => {
+                bonus
+                    && (0..rules.bonus_set_count(max))
+                        .any(|i| rules.bonus_set(max, i) == Some(candidates))
+            }
+        };
+        if !possible {
+            return false;
+        }
+        if self.bonus.is_empty() {
+            self.new_bonus_deck(rules, rng);
+        }
+        let used = match next {
+            NextHint::Exact(_) if !plain => true,
+            _ => next.is_bonus(),
+        };
+        if let Some(i) = self.bonus.iter().position(|&x| x == used) {
+            self.bonus.remove(i);
+        }
+        if let NextHint::Exact(card) = next {
+            if self.deck.contents.is_empty() {
+                self.deck.refill(&rules.deck, rng);
+            }
+            let i = self.deck.contents.iter().position(|&c| c == card).unwrap();
+            self.deck.contents.remove(i);
+        }
+        true
+    }
+    fn shuffle(&mut self, rng: &mut dyn RngCore) {
+        self.deck.contents.shuffle(rng);
+        self.bonus.shuffle(rng);
+    }
+}
+
+/// Deals the given hints in order, starting over when they run out, e.g. to
+/// script a bonus tile in a test.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "ScriptedHints")
+)]
+pub struct ScriptedTiles {
+    hints: Vec<NextHint>,
+    next: usize,
+}
+
+// The serialized form of ScriptedTiles, checked when read.
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+struct ScriptedHints {
+    hints: Vec<NextHint>,
+    next: usize,
+}
+
+impl TryFrom<ScriptedHints> for ScriptedTiles {
+    type Error = Error;
+
+    fn try_from(h: ScriptedHints) -> Result<Self, Error> {
+        let mut t = Self::try_new(h.hints)?;
+        t.next = h.next;
+        Ok(t)
+    }
+}
+
+impl ScriptedTiles {
+    /// Panics if hints is empty.
+    pub fn new(hints: Vec<NextHint>) -> Self {
+        Self::try_new(hints).unwrap_or_else(|e| panic!("{}", e))
+    }
+    /// Like new, but returns an error if hints is empty.
+    pub fn try_new(hints: Vec<NextHint>) -> Result<Self, Error> {
+        if hints.is_empty() {
+            return Err(Error::Invalid("no hints to deal".into()));
+        }
+        Ok(Self { hints, next: 0 })
+    }
+    fn peek(&self) -> NextHint {
+        self.hints[self.next % self.hints.len()]
+    }
+}
+
+impl TileSource for ScriptedTiles {
+    fn draw(&mut self, _: &Rules, _: Tile, _: &mut dyn RngCore) -> NextHint {
+        let h = self.peek();
+        self.next += 1;
+        h
+    }
+    fn hints(&self, _: &Rules, _: Tile) -> Vec<(NextHint, f64)> {
+        vec![(self.peek(), 1.0)]
+    }
+    fn observe(&mut self, _: &Rules, _: Tile, next: NextHint, _: &mut dyn RngCore) -> bool {
+        if next != self.peek() {
+            return false;
+        }
+        self.next += 1;
+        true
+    }
+}
+
+/// Deals each of the values with equal chance, independently of the last.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "UniformValues")
+)]
+pub struct UniformTiles {
+    values: Vec<Tile>,
+}
+
+// The serialized form of UniformTiles, checked when read.
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+struct UniformValues {
+    values: Vec<Tile>,
+}
+
+impl TryFrom<UniformValues> for UniformTiles {
+    type Error = Error;
+
+    fn try_from(u: UniformValues) -> Result<Self, Error> {
+        Self::try_new(u.values)
+    }
+}
+
+impl UniformTiles {
+    /// Panics if values is empty.  A value listed twice is twice as likely.
+    pub fn new(values: Vec<Tile>) -> Self {
+        Self::try_new(values).unwrap_or_else(|e| panic!("{}", e))
+    }
+    /// Like new, but returns an error if values is empty.
+    pub fn try_new(values: Vec<Tile>) -> Result<Self, Error> {
+        if values.is_empty() {
+            return Err(Error::Invalid("no values to deal".into()));
+        }
+        Ok(Self { values })
+    }
+}
+
+impl TileSource for UniformTiles {
+    fn draw(&mut self, _: &Rules, _: Tile, rng: &mut dyn RngCore) -> NextHint {
+        NextHint::Exact(self.values[rng.gen_range(0..self.values.len())])
+    }
+    fn hints(&self, _: &Rules, _: Tile) -> Vec<(NextHint, f64)> {
+        let d = Deck {
+            contents: self.values.clone(),
+        };
+        let n = self.values.len() as f64;
+        d.counts()
+            .into_iter()
+            .map(|(c, k)| (NextHint::Exact(c), k as f64 / n))
+            .collect()
+    }
+    fn observe(&mut self, _: &Rules, _: Tile, next: NextHint, _: &mut dyn RngCore) -> bool {
+        matches!(next, NextHint::Exact(c) if self.values.contains(&c))
+    }
+}
+
+/// Deals values with chances proportional to their weights, e.g. 2 and 4 at
+/// 9:1 as in 2048.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "Weights")
+)]
+pub struct WeightedTiles {
+    weights: Vec<(Tile, u32)>,
+}
+
+// The serialized form of WeightedTiles, checked when read.
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+struct Weights {
+    weights: Vec<(Tile, u32)>,
+}
+
+impl TryFrom<Weights> for WeightedTiles {
+    type Error = Error;
+
+    fn try_from(w: Weights) -> Result<Self, Error> {
+        Self::try_new(w.weights)
+    }
+}
+
+impl WeightedTiles {
+    /// Panics if the weights add up to 0 (or overflow).
+    pub fn new(weights: Vec<(Tile, u32)>) -> Self {
+        Self::try_new(weights).unwrap_or_else(|e| panic!("{}", e))
+    }
+    /// Deals each card of deck with a weight of the number of times it
+    /// appears there.  Panics if deck is empty.
+    pub fn from_deck(deck: &[Tile]) -> Self {
+        Self::new(
+            Deck {
+                contents: deck.to_vec(),
+            }
+            .counts(),
+        )
+    }
+    /// Like new, but returns an error if the weights add up to 0 (or
+    /// overflow).
+    pub fn try_new(weights: Vec<(Tile, u32)>) -> Result<Self, Error> {
+        let total = weights.iter().try_fold(0u32, |t, w| t.checked_add(w.1));
+        if total.unwrap_or(0) == 0 {
+            return Err(Error::Invalid("weights must add up to 1 or more".into()));
+        }
+        Ok(Self { weights })
+    }
+    fn total(&self) -> u32 {
+        self.weights.iter().map(|w| w.1).sum()
+    }
+}
+
+impl TileSource for WeightedTiles {
+    fn draw(&mut self, _: &Rules, _: Tile, rng: &mut dyn RngCore) -> NextHint {
+        let mut r = rng.gen_range(0..self.total());
+        for &(c, w) in &self.weights {
+            if r < w {
+                return NextHint::Exact(c);
+            }
+            r -= w;
+        }
+        unreachable!()
+    }
+    fn hints(&self, _: &Rules, _: Tile) -> Vec<(NextHint, f64)> {
+        let total = self.total() as f64;
+        self.weights
+            .iter()
+            .filter(|w| w.1 > 0)
+            .map(|&(c, w)| (NextHint::Exact(c), w as f64 / total))
+            .collect()
+    }
+    fn observe(&mut self, _: &Rules, _: Tile, next: NextHint, _: &mut dyn RngCore) -> bool {
+        match next {
+            NextHint::Exact(c) => self.weights.iter().any(|&(v, w)| v == c && w > 0),
+            NextHint::Bonus { .. } => false,
+        }
+    }
+}
+
+/// A tile source of your own, for Tiles::Custom.  Any TileSource that is
+/// Clone, PartialEq, Debug, Send and Sync is one.
+pub trait CustomTiles: TileSource + Debug + Send + Sync {
+    fn clone_box(&self) -> Box<dyn CustomTiles>;
+    /// Whether other is a source of the same type equal to this one.
+    fn eq_dyn(&self, other: &dyn CustomTiles) -> bool;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: TileSource + Clone + PartialEq + Debug + Send + Sync + 'static> CustomTiles for T {
+    fn clone_box(&self) -> Box<dyn CustomTiles> {
+        Box::new(self.clone())
+    }
+    fn eq_dyn(&self, other: &dyn CustomTiles) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn CustomTiles> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+impl PartialEq for Box<dyn CustomTiles> {
+    fn eq(&self, other: &Self) -> bool {
+        (**self).eq_dyn(&**other)
+    }
+}
+
+impl Eq for Box<dyn CustomTiles> {}
+
+impl TileSource for Box<dyn CustomTiles> {
+    fn draw(&mut self, rules: &Rules, max: Tile, rng: &mut dyn RngCore) -> NextHint {
+        (**self).draw(rules, max, rng)
+    }
+    fn hints(&self, rules: &Rules, max: Tile) -> Vec<(NextHint, f64)> {
+        (**self).hints(rules, max)
+    }
+    fn observe(&mut self, rules: &Rules, max: Tile, next: NextHint, rng: &mut dyn RngCore) -> bool {
+        (**self).observe(rules, max, next, rng)
+    }
+    fn shuffle(&mut self, rng: &mut dyn RngCore) {
+        (**self).shuffle(rng)
+    }
+}
+
+/// Selects one of the built-in tile sources, or one of your own, e.g. in
+/// Game.  A Custom source cannot be serialized.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Tiles {
+    Deck(DeckTiles),
+    Scripted(ScriptedTiles),
+    Uniform(UniformTiles),
+    Weighted(WeightedTiles),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(Box<dyn CustomTiles>),
+}
+
+impl Tiles {
+    /// The tile source a game under rules starts with.  If boosted, the
+    /// first bonus deck has no bonus cards; see DeckTiles::new.
+    pub fn for_rules(rules: &Rules, boosted: bool, rng: &mut dyn RngCore) -> Self {
+        match rules.dealing {
+            Dealing::Shuffled => Tiles::Deck(DeckTiles::new(rules, boosted, rng)),
+            Dealing::Independent => Tiles::Weighted(WeightedTiles::from_deck(&rules.deck)),
+        }
+    }
+    /// Like for_rules, but for a game rebuilt from s; see DeckTiles::from_state.
+    pub fn from_state(rules: &Rules, s: &State, rng: &mut dyn RngCore) -> Self {
+        match rules.dealing {
+            Dealing::Shuffled => Tiles::Deck(DeckTiles::from_state(rules, s, rng)),
+            Dealing::Independent => Tiles::Weighted(WeightedTiles::from_deck(&rules.deck)),
+        }
+    }
+    // Draws one of the tiles dealt when a game starts, never a bonus tile.
+    pub(crate) fn card(&mut self, rules: &Rules, rng: &mut dyn RngCore) -> Tile {
+        match self {
+            Tiles::Deck(t) => t.card(rules, rng),
+            t => t.draw(rules, Tile::EMPTY, rng).values()[0],
+        }
+    }
+    fn source(&self) -> &dyn TileSource {
+        match self {
+            Tiles::Deck(t) => t,
+            Tiles::Scripted(t) => t,
+            Tiles::Uniform(t) => t,
+            Tiles::Weighted(t) => t,
+            Tiles::Custom(t) => t,
+        }
+    }
+    fn source_mut(&mut self) -> &mut dyn TileSource {
+        match self {
+            Tiles::Deck(t) => t,
+            Tiles::Scripted(t) => t,
+            Tiles::Uniform(t) => t,
+            Tiles::Weighted(t) => t,
+            Tiles::Custom(t) => t,
+        }
+    }
+}
+
+impl TileSource for Tiles {
+    fn draw(&mut self, rules: &Rules, max: Tile, rng: &mut dyn RngCore) -> NextHint {
+        self.source_mut().draw(rules, max, rng)
+    }
+    fn hints(&self, rules: &Rules, max: Tile) -> Vec<(NextHint, f64)> {
+        self.source().hints(rules, max)
+    }
+    fn observe(&mut self, rules: &Rules, max: Tile, next: NextHint, rng: &mut dyn RngCore) -> bool {
+        self.source_mut().observe(rules, max, next, rng)
+    }
+    fn shuffle(&mut self, rng: &mut dyn RngCore) {
+        self.source_mut().shuffle(rng)
+    }
+}
+
+/// Places each tile in one of the open positions, picked uniformly.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RandomPlacement;
+
+impl SpawnPlacement for RandomPlacement {
+    fn place(&mut self, open: &[usize], rng: &mut dyn RngCore) -> usize {
+        open[rng.gen_range(0..open.len())]
+    }
+}
+
+/// Places each tile in the first open position.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FirstSlot;
+
+impl SpawnPlacement for FirstSlot {
+    fn place(&mut self, open: &[usize], _: &mut dyn RngCore) -> usize {
+        open[0]
+    }
+    fn probabilities(&self, open: &[usize]) -> Vec<(usize, f64)> {
+        vec![(open[0], 1.0)]
+    }
+}
+
+/// Places tiles at the given positions in order.  When the next position is
+/// not open, or the positions have run out, the tile goes in the first open
+/// position instead.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ScriptedPlacement {
+    positions: Vec<usize>,
+    next: usize,
+}
+
+impl ScriptedPlacement {
+    pub fn new(positions: Vec<usize>) -> Self {
+        Self { positions, next: 0 }
+    }
+    fn peek(&self, open: &[usize]) -> usize {
+        let pos = self.positions.get(self.next).copied();
+        pos.filter(|p| open.contains(p)).unwrap_or(open[0])
+    }
+}
+
+impl SpawnPlacement for ScriptedPlacement {
+    fn place(&mut self, open: &[usize], _: &mut dyn RngCore) -> usize {
+        let pos = self.peek(open);
+        self.next += 1;
+        pos
+    }
+    fn probabilities(&self, open: &[usize]) -> Vec<(usize, f64)> {
+        vec![(self.peek(open), 1.0)]
+    }
+}
+
+/// A placement of your own, for Placement::Custom.  Any SpawnPlacement that
+/// is Clone, PartialEq, Debug, Send and Sync is one.
+pub trait CustomPlacement: SpawnPlacement + Debug + Send + Sync {
+    fn clone_box(&self) -> Box<dyn CustomPlacement>;
+    /// Whether other is a placement of the same type equal to this one.
+    fn eq_dyn(&self, other: &dyn CustomPlacement) -> bool;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: SpawnPlacement + Clone + PartialEq + Debug + Send + Sync + 'static> CustomPlacement for T {
+    fn clone_box(&self) -> Box<dyn CustomPlacement> {
+        Box::new(self.clone())
+    }
+    fn eq_dyn(&self, other: &dyn CustomPlacement) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn CustomPlacement> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+impl PartialEq for Box<dyn CustomPlacement> {
+    fn eq(&self, other: &Self) -> bool {
+        (**self).eq_dyn(&**other)
+    }
+}
+
+impl Eq for Box<dyn CustomPlacement> {}
+
+/// Selects one of the built-in placements, or one of your own, e.g. in Game.
+/// A Custom placement cannot be serialized.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Placement {
+    #[default]
+    Random,
+    FirstSlot,
+    Scripted(ScriptedPlacement),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(Box<dyn CustomPlacement>),
+}
+
+impl SpawnPlacement for Placement {
+    fn place(&mut self, open: &[usize], rng: &mut dyn RngCore) -> usize {
+        match self {
+            Placement::Random => RandomPlacement.place(open, rng),
+            Placement::FirstSlot => FirstSlot.place(open, rng),
+            Placement::Scripted(p) => p.place(open, rng),
+            Placement::Custom(p) => p.place(open, rng),
+        }
+    }
+    fn probabilities(&self, open: &[usize]) -> Vec<(usize, f64)> {
+        match self {
+            Placement::Random => RandomPlacement.probabilities(open),
+            Placement::FirstSlot => FirstSlot.probabilities(open),
+            Placement::Scripted(p) => p.probabilities(open),
+            Placement::Custom(p) => p.probabilities(open),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{RngCore, SeedableRng};
+    use rand_pcg::Pcg32;
+
+    use crate::board::Board;
+    use crate::game::{NextHint, State};
+    use crate::rules::Rules;
+    #[cfg(feature = "serde")]
+    use crate::spawn::ScriptedTiles;
+    use crate::spawn::{
+        DeckTiles, Placement, ScriptedPlacement, SpawnPlacement, TileSource, Tiles, UniformTiles,
+        WeightedTiles,
+    };
+    use crate::tile::Tile;
+
+    #[test]
+    fn sources() {
+        let rules = Rules::twenty48();
+        let mut rng = Pcg32::seed_from_u64(0);
+        let mut w = WeightedTiles::new(vec![(Tile::TWO, 9), (Tile::new(4), 1), (Tile::new(8), 0)]);
+        let hints = w.hints(&rules, Tile::TWO);
+        assert_eq!(hints.len(), 2);
+        assert!((hints[0].1 - 0.9).abs() < 1e-9);
+        for _ in 0..100 {
+            let h = w.draw(&rules, Tile::TWO, &mut rng);
+            assert!(h == NextHint::Exact(Tile::TWO) || h == NextHint::Exact(Tile::new(4)));
+        }
+        assert!(!w.observe(&rules, Tile::TWO, NextHint::Exact(Tile::new(8)), &mut rng));
+        assert_eq!(
+            WeightedTiles::from_deck(&rules.deck),
+            WeightedTiles::new(vec![(Tile::TWO, 9), (Tile::new(4), 1)])
+        );
+        let u = UniformTiles::new(vec![Tile::TWO, Tile::TWO, Tile::new(4)]);
+        let hints = u.hints(&rules, Tile::TWO);
+        assert_eq!(hints[0], (NextHint::Exact(Tile::TWO), 2.0 / 3.0));
+    }
+
+    #[test]
+    fn observed_deck() {
+        let rules = Rules::standard();
+        let mut rng = Pcg32::seed_from_u64(0);
+        let deck = |bonus_left, bonus_cards_left| {
+            let s = State {
+                board: Board::empty(4, 4),
+                deck: vec![Tile::ONE],
+                bonus_left,
+                bonus_cards_left,
+                next: NextHint::Exact(Tile::ONE),
+            };
+            DeckTiles::from_state(&rules, &s, &mut Pcg32::seed_from_u64(0))
+        };
+        let (t48, t384) = (Tile::new(48), Tile::new(384));
+        let bonus =
+            |v: &[u32]| NextHint::bonus(&v.iter().map(|&v| Tile::new(v)).collect::<Vec<_>>());
+        let one = NextHint::Exact(Tile::ONE);
+        // The bonus card of this bonus deck has already come up.
+        assert!(!deck(5, 0).observe(&rules, t48, bonus(&[6]), &mut rng));
+        // Only the bonus card is left: a deck card cannot come up once unlocked.
+        assert!(!deck(1, 1).observe(&rules, t48, one, &mut rng));
+        assert!(deck(1, 1).observe(&rules, Tile::THREE, one, &mut rng));
+        // Candidates must be a whole bonus set.
+        assert!(!deck(5, 1).observe(&rules, t384, bonus(&[12, 24]), &mut rng));
+        assert!(deck(5, 1).observe(&rules, t384, bonus(&[12, 24, 48]), &mut rng));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn checked_when_read() {
+        let w: WeightedTiles = serde_json::from_str(r#"{"weights":[[2,9],[4,1]]}"#).unwrap();
+        assert_eq!(
+            w,
+            WeightedTiles::new(vec![(Tile::TWO, 9), (Tile::new(4), 1)])
+        );
+        assert!(serde_json::from_str::<WeightedTiles>(r#"{"weights":[[2,0]]}"#).is_err());
+        assert!(serde_json::from_str::<UniformTiles>(r#"{"values":[]}"#).is_err());
+        assert!(serde_json::from_str::<ScriptedTiles>(r#"{"hints":[],"next":0}"#).is_err());
+        assert!(ScriptedTiles::try_new(vec![]).is_err());
+    }
+
+    // Counts up from 1, for any rules.
+    #[derive(Clone, PartialEq, Debug)]
+    struct Counting(u32);
+
+    impl TileSource for Counting {
+        fn draw(&mut self, _: &Rules, _: Tile, _: &mut dyn RngCore) -> NextHint {
+            self.0 += 1;
+            NextHint::Exact(Tile::new(self.0))
+        }
+        fn hints(&self, _: &Rules, _: Tile) -> Vec<(NextHint, f64)> {
+            vec![(NextHint::Exact(Tile::new(self.0 + 1)), 1.0)]
+        }
+        fn observe(&mut self, _: &Rules, _: Tile, _: NextHint, _: &mut dyn RngCore) -> bool {
+            false
+        }
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    struct LastSlot;
+
+    impl SpawnPlacement for LastSlot {
+        fn place(&mut self, open: &[usize], _: &mut dyn RngCore) -> usize {
+            open[open.len() - 1]
+        }
+    }
+
+    #[test]
+    fn custom() {
+        let rules = Rules::standard();
+        let mut rng = Pcg32::seed_from_u64(0);
+        let mut t = Tiles::Custom(Box::new(Counting(0)));
+        assert_eq!(
+            t.draw(&rules, Tile::ONE, &mut rng),
+            NextHint::Exact(Tile::ONE)
+        );
+        let u = t.clone();
+        assert_eq!(t, u);
+        t.draw(&rules, Tile::ONE, &mut rng);
+        assert_ne!(t, u);
+        assert_ne!(
+            u,
+            Tiles::Custom(Box::new(UniformTiles::new(vec![Tile::ONE])))
+        );
+        let mut p = Placement::Custom(Box::new(LastSlot));
+        assert_eq!(p.place(&[1, 5, 9], &mut rng), 9);
+        assert_eq!(p.clone(), p);
+        assert_ne!(p, Placement::FirstSlot);
+        #[cfg(feature = "serde")]
+        assert!(serde_json::to_string(&p).is_err());
+    }
+
+    #[test]
+    fn placements() {
+        let mut rng = Pcg32::seed_from_u64(0);
+        let mut p = Placement::Scripted(ScriptedPlacement::new(vec![5, 1]));
+        assert_eq!(p.place(&[1, 5, 9], &mut rng), 5);
+        assert_eq!(p.place(&[3, 9], &mut rng), 3);
+        assert_eq!(p.place(&[3, 9], &mut rng), 3);
+        assert_eq!(Placement::FirstSlot.place(&[7, 8], &mut rng), 7);
+        assert_eq!(p.probabilities(&[3, 9]), [(3, 1.0)]);
+        assert_eq!(
+            Placement::Random.probabilities(&[3, 9]),
+            [(3, 0.5), (9, 0.5)]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use alloc::{format, string::ToString};
+use core::{cmp::Ordering, fmt, str::FromStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// The face value of a tile, or Tile::EMPTY for an empty cell.
+///
+/// Threes! tiles are 1, 2 and 3 * 2^k, numbered by rank: 1 and 2 are ranks 1
+/// and 2, and 3 * 2^k is rank k + 3.  The other merge rules use other values,
+/// so a Tile may hold any value (see Tile::new); rank, score and the checked
+/// conversions are those of Threes!.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct Tile(u32);
+
+impl Tile {
+    pub const EMPTY: Tile = Tile(0);
+    pub const ONE: Tile = Tile(1);
+    pub const TWO: Tile = Tile(2);
+    pub const THREE: Tile = Tile(3);
+    /// The rank of the largest Threes! tile that fits in a u32, 3 * 2^30.
+    pub const MAX_RANK: u8 = 33;
+
+    /// A tile of any value, including ones that are not Threes! tiles; use
+    /// Tile::try_from to check the value.
+    pub const fn new(value: u32) -> Self {
+        Tile(value)
+    }
+    /// The Threes! tile 3 * 2^k.  Panics if k > 30.
+    pub const fn three_times_pow2(k: u32) -> Self {
+        assert!(k <= 30, "tile out of range");
+        Tile(3 << k)
+    }
+    /// The Threes! tile of the given rank, where rank 0 is the empty cell.
+    pub fn from_rank(rank: u8) -> Option<Self> {
+        match rank {
+            0..=2 => Some(Tile(rank as u32)),
+            r if r <= Self::MAX_RANK => Some(Self::three_times_pow2(r as u32 - 3)),
+            _ => None,
+        }
+    }
+    pub const fn value(self) -> u32 {
+        self.0
+    }
+    /// The tile's Threes! rank (0 for an empty cell), or None if it is not a
+    /// Threes! tile.
+    pub fn rank(self) -> Option<u8> {
+        match self.0 {
+            0..=2 => Some(self.0 as u8),
+            v if v % 3 == 0 && (v / 3).is_power_of_two() => Some((v / 3).ilog2() as u8 + 3),
+            _ => None,
+        }
+    }
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+    /// Reports whether this is a Threes! tile or the empty cell.
+    pub fn is_valid(self) -> bool {
+        self.rank().is_some()
+    }
+    /// The points the tile is worth at the end of a Threes! game: 3 is worth
+    /// 3, and every doubling triples that.
+    pub fn score(self) -> u64 {
+        match self.rank() {
+            Some(r) if r >= 3 => 3u64.pow(r as u32 - 2),
+            _ => 0,
+        }
+    }
+}
+
+impl From<Tile> for u32 {
+    fn from(t: Tile) -> Self {
+        t.0
+    }
+}
+
+impl TryFrom<u32> for Tile {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self, Error> {
+        let t = Tile(value);
+        if !t.is_valid() {
+            return Err(Error::InvalidTile(value));
+        }
+        Ok(t)
+    }
+}
+
+impl PartialEq<u32> for Tile {
+    fn eq(&self, other: &u32) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<u32> for Tile {
+    fn partial_cmp(&self, other: &u32) -> Option<Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+/// Shows the value, or "." for an empty cell; width and alignment apply.
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            f.pad(".")
+        } else {
+            f.pad(&self.0.to_string())
+        }
+    }
+}
+
+/// Parses a Threes! tile's value, or "." or "0" for an empty cell.
+impl FromStr for Tile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if s == "." {
+            return Ok(Tile::EMPTY);
+        }
+        let v: u32 = s
+            .parse()
+            .map_err(|_| Error::Invalid(format!("bad tile {:?}", s)))?;
+        Tile::try_from(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::tile::Tile;
+
+    #[test]
+    fn ranks() {
+        for r in 0..=Tile::MAX_RANK {
+            assert_eq!(Tile::from_rank(r).unwrap().rank(), Some(r));
+        }
+        assert_eq!(Tile::from_rank(Tile::MAX_RANK + 1), None);
+        assert_eq!(Tile::three_times_pow2(0), Tile::THREE);
+        assert_eq!(Tile::three_times_pow2(6).rank(), Some(9));
+        assert_eq!(Tile::new(4).rank(), None);
+        assert_eq!(Tile::new(12).score(), 27);
+        assert_eq!(Tile::TWO.score(), 0);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("192".parse(), Ok(Tile::three_times_pow2(6)));
+        assert_eq!(".".parse(), Ok(Tile::EMPTY));
+        assert_eq!("0".parse(), Ok(Tile::EMPTY));
+        assert_eq!("5".parse::<Tile>(), Err(Error::InvalidTile(5)));
+        assert!("x".parse::<Tile>().is_err());
+        assert!(Tile::try_from(9).is_err());
+        assert_eq!(format!("{:>4}|{}", Tile::new(48), Tile::EMPTY), "  48|.");
+    }
+}
@@ -0,0 +1,159 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adversary::Adversary,
+    board::Positions,
+    game::{Error, Game, Move, NextHint},
+    tile::Tile,
+};
+
+/// Whose turn it is in a Versus game.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Turn {
+    /// The slider picks a direction.
+    Slide,
+    /// The spawner picks where the new tile lands, and which of the
+    /// candidates it is.
+    Spawn,
+}
+
+/// Two-player Threes: the slider moves the board and the spawner places each
+/// new tile in one of the cells the move vacated, choosing its value among
+/// the candidates for a bonus tile.  The spawner may be a person (place) or
+/// any Adversary (play_adversary).
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Versus {
+    g: Game,
+    // The cells the last slide vacated; empty on the slider's turn.
+    open: Positions,
+    moves: usize,
+    over: bool,
+}
+
+impl Versus {
+    pub fn new(g: Game) -> Self {
+        let over = !g.can_move();
+        Self {
+            g,
+            open: Positions::EMPTY,
+            moves: 0,
+            over,
+        }
+    }
+    /// The game; on the spawner's turn, its board has moved but the new tile
+    /// has not landed.
+    pub fn game(&self) -> &Game {
+        &self.g
+    }
+    pub fn turn(&self) -> Turn {
+        if self.open.is_empty() {
+            Turn::Slide
+        } else {
+            Turn::Spawn
+        }
+    }
+    /// The cells the new tile may land in on the spawner's turn.
+    pub fn open(&self) -> &[usize] {
+        &self.open
+    }
+    /// The values the new tile may have.
+    pub fn candidates(&self) -> NextHint {
+        self.g.next()
+    }
+    /// The number of completed turn pairs.
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+    /// Moves the board for the slider and hands the turn to the spawner.
+    /// Returns IllegalMove if nothing moved or it is not the slider's turn,
+    /// and GameOver once the game has ended.
+    pub fn slide(&mut self, mv: Move) -> Result<(), Error> {
+        if self.over {
+            return Err(Error::GameOver);
+        }
+        if self.turn() != Turn::Slide {
+            return Err(Error::IllegalMove);
+        }
+        self.open = self.g.slide(mv);
+        if self.open.is_empty() {
+            return Err(Error::IllegalMove);
+        }
+        Ok(())
+    }
+    /// Places value at position for the spawner and hands the turn back to
+    /// the slider.  Returns IllegalSpawn if the spawn is not allowed or it is
+    /// not the spawner's turn, and GameOver if the slider cannot move
+    /// afterwards.
+    pub fn place(&mut self, position: usize, value: Tile) -> Result<NextHint, Error> {
+        if self.over {
+            return Err(Error::GameOver);
+        }
+        if !self.open.contains(&position) || self.candidates().probability(value) == 0.0 {
+            return Err(Error::IllegalSpawn);
+        }
+        self.open.clear();
+        self.moves += 1;
+        let res = self.g.spawn(position, value);
+        self.over = res == Err(Error::GameOver);
+        res
+    }
+    /// Lets adv take the spawner's turn.
+    pub fn play_adversary<A: Adversary + ?Sized>(
+        &mut self,
+        adv: &mut A,
+    ) -> Result<NextHint, Error> {
+        if self.turn() != Turn::Spawn {
+            return Err(Error::IllegalSpawn);
+        }
+        let (pos, c) = adv.choose(&self.g, &self.open, self.candidates().values());
+        self.place(pos, c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adversary::WorstCase;
+    use crate::board::Board;
+    use crate::game::{Error, Game, Move};
+    use crate::tile::Tile;
+    use crate::versus::{Turn, Versus};
+
+    #[test]
+    fn turns() {
+        let b = Board::new([3, 0, 1, 2, 6, 6, 1, 2, 12, 6, 3, 1, 0, 3, 6, 6]);
+        let mut v = Versus::new(Game::with_board(b, &[6, 12]));
+        assert_eq!(v.turn(), Turn::Slide);
+        assert_eq!(v.place(3, Tile::new(6)), Err(Error::IllegalSpawn));
+        assert_eq!(v.slide(Move::Left), Ok(()));
+        assert_eq!(v.turn(), Turn::Spawn);
+        assert_eq!(v.open(), &[3, 7, 15]);
+        assert_eq!(v.slide(Move::Left), Err(Error::IllegalMove));
+        assert_eq!(v.place(0, Tile::new(6)), Err(Error::IllegalSpawn));
+        assert_eq!(v.place(7, Tile::THREE), Err(Error::IllegalSpawn));
+        assert!(v.place(7, Tile::new(12)).is_ok());
+        assert_eq!(v.game().board().cells()[7], 12);
+        assert_eq!((v.turn(), v.moves()), (Turn::Slide, 1));
+    }
+
+    #[test]
+    fn adversary() {
+        // Left only vacates 3, and a 6 there ends the game.
+        let b = Board::new([1, 2, 12, 24, 6, 24, 6, 12, 12, 6, 12, 6, 3, 48, 24, 12]);
+        let mut v = Versus::new(Game::with_board(b, &[6]));
+        assert_eq!(
+            v.play_adversary(&mut WorstCase { depth: 1 }),
+            Err(Error::IllegalSpawn)
+        );
+        v.slide(Move::Left).unwrap();
+        let res = v.play_adversary(&mut WorstCase { depth: 1 });
+        assert_eq!(res, Err(Error::GameOver));
+        assert!(v.is_over());
+        assert_eq!(v.slide(Move::Right), Err(Error::GameOver));
+    }
+}
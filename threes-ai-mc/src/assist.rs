@@ -0,0 +1,184 @@
+use std::io::{self, BufRead, Write};
+
+use threes::{
+    analysis::Evaluator,
+    board::Board,
+    game::{Candidates, Error, Game, Move, NextHint, State},
+    rules::Rules,
+    tile::Tile,
+};
+use threes_ai_mc::MonteCarlo;
+
+// Usage: threes-ai-mc assist [--iters N]
+//
+// Mirrors a game played elsewhere (e.g. the mobile Threes!): the user enters
+// the starting board and next tile, then after every move reports where the
+// new tile landed and what the next one is.  The remaining deck and bonus
+// deck are tracked from those observations, and the best move is suggested
+// before every turn.
+pub fn assist(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let mut mc = MonteCarlo { iters: 5000 };
+    match (args.next().as_deref(), args.next().map(|v| v.parse())) {
+        (None, _) => {}
+        (Some("--iters"), Some(Ok(n))) => mc.iters = n,
+        _ => return Err(invalid("usage: threes-ai-mc assist [--iters N]")),
+    }
+    let mut input = io::stdin().lock().lines();
+    let mut ask = |prompt: &str| -> io::Result<String> {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        input
+            .next()
+            .unwrap_or_else(|| Err(io::ErrorKind::UnexpectedEof.into()))
+    };
+
+    println!("Positions are numbered row by row:");
+    for row in (0..16).collect::<Vec<_>>().chunks(4) {
+        println!("{:?}", row);
+    }
+    let mut cells = Vec::new();
+    while cells.len() < 16 {
+        let line = ask(&format!(
+            "Board, row by row, 0 for empty ({} left): ",
+            16 - cells.len()
+        ))?;
+        match parse_tiles(&line) {
+            Some(v) if cells.len() + v.len() <= 16 => cells.extend(v),
+            _ => println!("Enter up to {} tiles.", 16 - cells.len()),
+        }
+    }
+    let board = Board::from_tiles(4, 4, &cells);
+    let rules = Rules::standard();
+    let next = loop {
+        match parse_tiles(&ask("Next tile: ")?).and_then(|v| hint(v, &rules.deck)) {
+            Some(h) => break h,
+            _ => println!("Enter the next tile's value."),
+        }
+    };
+    // Assume the board and next were dealt from a fresh deck.
+    let mut deck = rules.deck.clone();
+    let card = match next {
+        NextHint::Exact(c) => Some(c),
+        NextHint::Bonus { .. } => None,
+    };
+    for c in board.cells().iter().chain(&card) {
+        if let Some(i) = deck.iter().position(|d| d == c) {
+            deck.remove(i);
+        }
+    }
+    let mut g = Game::from_state(
+        rules,
+        State {
+            board,
+            deck,
+            bonus_left: 21,
+            bonus_cards_left: 1,
+            next,
+        },
+    );
+
+    loop {
+        println!("\n{:#}\nNext: {:#}", g.board(), g.next());
+        let mut best = None;
+        for mv in Move::ALL {
+            if let Some(v) = mc.evaluate(&g, mv) {
+                print!("  {:?}: {:.1}", mv, v);
+                if best.is_none_or(|(_, b)| v > b) {
+                    best = Some((mv, v));
+                }
+            }
+        }
+        let Some((best, _)) = best else {
+            println!("\nGame over.");
+            return Ok(());
+        };
+        println!("\nSuggested: {:?}", best);
+
+        let mv = match ask("Your move (u/d/l/r, q to quit): ")?.trim() {
+            "u" => Move::Up,
+            "d" => Move::Down,
+            "l" => Move::Left,
+            "r" => Move::Right,
+            "q" => return Ok(()),
+            _ => continue,
+        };
+        let Ok(p) = g.shift(mv) else {
+            println!("That move is not possible.");
+            continue;
+        };
+        let (open, candidates) = (p.open().to_vec(), p.candidates());
+        let candidates = candidates.values();
+        let (pos, c) = match (&open[..], candidates) {
+            ([pos], [c]) => (*pos, *c),
+            _ => {
+                let prompt = format!(
+                    "New tile landed at {:?}, value {:#}: ",
+                    open,
+                    p.candidates()
+                );
+                match parse_nums(&ask(&prompt)?).as_deref() {
+                    Some([pos]) if candidates.len() == 1 => (*pos as usize, candidates[0]),
+                    Some([pos, c]) => (*pos as usize, Tile::new(*c)),
+                    _ => (usize::MAX, Tile::EMPTY),
+                }
+            }
+        };
+        let line = ask("New next tile (a value, several for a bonus, or +): ")?;
+        let next = match line.trim() {
+            // The bonus tile, if its candidates are certain.
+            "+" => {
+                let rules = p.game().rules();
+                let max = p.game().board().cells().iter().copied().max();
+                let max = max.unwrap_or(Tile::EMPTY);
+                match rules.bonus_set_count(max) {
+                    1 => rules
+                        .bonus_set(max, 0)
+                        .map(|candidates| NextHint::Bonus { candidates }),
+                    _ => None,
+                }
+            }
+            s => parse_tiles(s).and_then(|v| hint(v, &p.game().rules().deck)),
+        };
+        let Some(next) = next else {
+            println!("Enter one to three values, or + while only one bonus set is possible; the move was undone.");
+            continue;
+        };
+        match p.resolve_observed(pos, c, next) {
+            Ok(_) => {}
+            Err(Error::GameOver) => {
+                println!("\n{:#}\nGame over.", g.board());
+                return Ok(());
+            }
+            Err(_) => println!("That does not match the game; the move was undone."),
+        }
+    }
+}
+
+// A single value from the deck is a card; anything else is a bonus tile.
+fn hint(vals: Vec<Tile>, deck: &[Tile]) -> Option<NextHint> {
+    match vals[..] {
+        [c] if deck.contains(&c) => Some(NextHint::Exact(c)),
+        _ => Candidates::try_from(vals)
+            .ok()
+            .map(|candidates| NextHint::Bonus { candidates }),
+    }
+}
+
+fn parse_nums(s: &str) -> Option<Vec<u32>> {
+    s.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+        .map(|t| t.parse().ok())
+        .collect()
+}
+
+// Like parse_nums, but every value must be a tile.
+fn parse_tiles(s: &str) -> Option<Vec<Tile>> {
+    s.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+        .map(|t| t.parse().ok())
+        .collect()
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
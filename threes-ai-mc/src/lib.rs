@@ -0,0 +1,130 @@
+use rand::prelude::*;
+use std::thread;
+use threes::{
+    analysis::Evaluator,
+    game::{self, Game, Move},
+};
+
+/// Scores moves by playing many random games to completion from the position
+/// reached after the move, with the unknowns (deck order, bonus position)
+/// reshuffled for each playout.
+#[derive(Copy, Clone, Debug)]
+pub struct MonteCarlo {
+    pub iters: i32,
+}
+
+impl Default for MonteCarlo {
+    fn default() -> Self {
+        Self { iters: 100000 }
+    }
+}
+
+impl MonteCarlo {
+    /// Returns the average playout length after mv plus the worst playout
+    /// length, or 0 if mv is illegal.  progress is called periodically with
+    /// the iteration count and the score so far.
+    pub fn score(&self, g: &Game, mv: Move, progress: impl FnMut(i32, f32)) -> f32 {
+        let (avg, worst) = self.playouts(g, mv, progress);
+        avg + worst as f32
+    }
+    // Returns the average and the worst playout length.
+    fn playouts(&self, g: &Game, mv: Move, mut progress: impl FnMut(i32, f32)) -> (f32, i32) {
+        let mut avg = 0f32;
+        let mut worst = 9999;
+        // Reseeding from this thread's rng is much cheaper than from entropy.
+        let mut rng = thread_rng();
+        for i in 0..self.iters {
+            let mut g = g.clone();
+            g.rerand_with(&mut rng);
+            match g.apply(mv) {
+                Err(game::Error::IllegalMove) => {
+                    return (0.0, 0);
+                }
+                // The move itself ends the game: a playout of length 0.
+                Err(game::Error::GameOver) => {
+                    worst = 0;
+                    continue;
+                }
+                _ => {}
+            }
+            let c = run(&mut g);
+            if i % 100 == 50 {
+                progress(i, (avg / i as f32) + worst as f32);
+            }
+            avg += c as f32;
+            if c < worst {
+                worst = c;
+            }
+        }
+        (avg / (self.iters as f32), worst)
+    }
+}
+
+impl Evaluator for MonteCarlo {
+    // Splits the playouts across all available cores.
+    fn evaluate(&mut self, g: &Game, mv: Move) -> Option<f32> {
+        if !g.can_move() || g.clone().apply(mv) == Err(game::Error::IllegalMove) {
+            return None;
+        }
+        let threads = thread::available_parallelism().map_or(1, |n| n.get() as i32);
+        let part = Self {
+            iters: (self.iters / threads).max(1),
+        };
+        let parts: Vec<(f32, i32)> = thread::scope(|s| {
+            let handles: Vec<_> = (0..threads)
+                .map(|_| {
+                    let g = g.clone();
+                    s.spawn(move || part.playouts(&g, mv, |_, _| {}))
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let avg = parts.iter().map(|p| p.0).sum::<f32>() / threads as f32;
+        let worst = parts.iter().map(|p| p.1).min().unwrap();
+        Some(avg + worst as f32)
+    }
+}
+
+// Plays random moves until the game ends and returns how many were made.
+fn run(g: &mut Game) -> i32 {
+    let mut c = 0;
+    loop {
+        let mv = Move::ALL[thread_rng().gen_range(0..4)];
+        let res = g.apply(mv);
+        if res == Err(game::Error::GameOver) {
+            return c;
+        }
+        if res.is_ok() {
+            c += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MonteCarlo;
+    use threes::{
+        board::Board,
+        game::{Game, Move, NextHint, State},
+        rules::Rules,
+        tile::Tile,
+    };
+
+    #[test]
+    fn losing_move() {
+        let g = Game::from_state_seeded(
+            Rules::standard(),
+            State {
+                board: Board::new([3, 6, 3, 6, 6, 3, 6, 3, 3, 6, 3, 6, 0, 6, 3, 6]),
+                deck: vec![],
+                bonus_left: 0,
+                bonus_cards_left: 0,
+                next: NextHint::Exact(Tile::ONE),
+            },
+            0,
+        );
+        let mc = MonteCarlo { iters: 20 };
+        assert_eq!(mc.score(&g, Move::Left, |_, _| {}), 0.0);
+        assert!(mc.score(&g, Move::Down, |_, _| {}) > 0.0);
+    }
+}
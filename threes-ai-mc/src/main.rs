@@ -6,9 +6,20 @@ use crossterm::{
     ExecutableCommand,
 };
 use rand::prelude::*;
-use threes::game::{self, Game};
+use threes::{
+    analysis::{self, Evaluator},
+    daily::ResultCode,
+    game::{Game, Move, NextHint},
+    generator::Generator,
+    replay::Replay,
+    tile::Tile,
+};
+use threes_ai_mc::MonteCarlo;
+
+mod assist;
 
 use std::{
+    env, fs,
     io::{self, stdout, Write},
     sync::{atomic::AtomicBool, Arc},
     thread,
@@ -54,109 +65,183 @@ fn printone(n: u32) -> io::Result<()> {
 fn printboard(g: &Game, mv: Option<Move>, best: f32) -> io::Result<()> {
     stdout().execute(cursor::MoveTo(1, 1))?;
     write!(stdout(), "Next: ")?;
-    match g.next().as_slice() {
-        [n] => printone(*n)?,
-        [n @ ..] => {
+    match g.next() {
+        NextHint::Exact(n) => printone(n.value())?,
+        hint => {
             stdout().execute(style::PrintStyledContent(
-                format!("{:?}", n)
+                format!("{:#}", hint)
                     .with(style::Color::Black)
                     .on(style::Color::Grey),
             ))?;
-            ()
         }
     };
 
+    let b = g.board();
+    let (w, h) = (b.width(), b.height() as u16);
     stdout().execute(cursor::MoveTo(1, 2))?;
-    write!(stdout(), "----------------")?;
+    write!(stdout(), "{}", "-".repeat(4 * w))?;
 
-    let b = g.board().0;
-    for row in 0..4 {
+    for row in 0..h {
         stdout().execute(cursor::MoveTo(1, 3 + row))?;
-        for col in 0..4 {
-            let n = b[(row * 4 + col) as usize];
+        for col in 0..w {
+            let n = b.get(row as usize, col).unwrap().value();
             if n < 1000 {
                 print!(" ");
             }
             printone(n)?;
         }
     }
-    if mv.is_some() {
-        stdout().execute(cursor::MoveTo(1, 8))?;
-        write!(stdout(), "Moved {:?} ({:5.1})", mv.unwrap(), best)?;
+    if let Some(mv) = mv {
+        stdout().execute(cursor::MoveTo(1, 4 + h))?;
+        write!(stdout(), "Moved {:?} ({:5.1})", mv, best)?;
     }
-    stdout().execute(cursor::MoveTo(1, 10))?;
+    stdout().execute(cursor::MoveTo(1, 6 + h))?;
     write!(stdout(), "Esc or 'q' to exit.")?;
-    stdout().execute(cursor::MoveTo(1, 11))?;
+    stdout().execute(cursor::MoveTo(1, 7 + h))?;
     Ok(())
 }
 
-#[derive(Copy, Debug, Clone)]
-enum Move {
-    Up,
-    Down,
-    Left,
-    Right,
+fn score(g: &Game, mv: Move) -> f32 {
+    MonteCarlo::default().score(g, mv, |_, sc| {
+        stdout().execute(cursor::MoveTo(15, 12)).ok();
+        write!(stdout(), "score={:5.1}...", sc).ok();
+    })
 }
 
-const ITERS: i32 = 100000;
+// Usage: threes-ai-mc analyze <replay.json> [--json] [--iters N] [--threshold F]
+fn analyze(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let usage = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: threes-ai-mc analyze <replay.json> [--json] [--iters N] [--threshold F]",
+        )
+    };
+    let path = args.next().ok_or_else(usage)?;
+    let mut json = false;
+    let mut mc = MonteCarlo { iters: 10000 };
+    let mut threshold = 0.2;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--iters" => mc.iters = args.next().and_then(|v| v.parse().ok()).ok_or_else(usage)?,
+            "--threshold" => {
+                threshold = args.next().and_then(|v| v.parse().ok()).ok_or_else(usage)?
+            }
+            _ => return Err(usage()),
+        }
+    }
+    let replay: Replay = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let report = analysis::analyze(&replay, &mut mc, threshold);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report);
+    }
+    Ok(())
+}
 
-fn score(g: Game, mv: Move) -> f32 {
-    let mut avg = 0f32;
-    let mut worst = 9999;
-    let backup = g.clone();
-    for i in 0..ITERS {
-        let mut g = backup.clone();
-        g.rerand();
-        let res = match mv {
-            Move::Up => g.up(),
-            Move::Down => g.down(),
-            Move::Left => g.left(),
-            Move::Right => g.right(),
+// Usage: threes-ai-mc generate [--count N] [--depth K] [--margin F] [--seed S]
+//                              [--ai] [--out DIR]
+//
+// Plays games from consecutive seeds (at random, or with --ai by Monte Carlo)
+// and prints every position with a clear best move as a JSON line, until N
+// are found.  With --out, each one's puzzle is also written to DIR for
+// `terminal --puzzle`.
+fn generate(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let usage = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: threes-ai-mc generate [--count N] [--depth K] [--margin F] [--seed S] [--ai] [--out DIR]",
+        )
+    };
+    let mut gen = Generator::default();
+    let (mut count, mut seed, mut ai, mut out) = (10, 0u64, false, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => count = args.next().and_then(|v| v.parse().ok()).ok_or_else(usage)?,
+            "--depth" => gen.depth = args.next().and_then(|v| v.parse().ok()).ok_or_else(usage)?,
+            "--margin" => {
+                gen.margin = args.next().and_then(|v| v.parse().ok()).ok_or_else(usage)?
+            }
+            "--seed" => seed = args.next().and_then(|v| v.parse().ok()).ok_or_else(usage)?,
+            "--ai" => ai = true,
+            "--out" => out = Some(args.next().ok_or_else(usage)?),
+            _ => return Err(usage()),
+        }
+    }
+    if let Some(dir) = &out {
+        fs::create_dir_all(dir)?;
+    }
+    let mut mc = MonteCarlo { iters: 200 };
+    let mut found = 0;
+    while found < count {
+        let policy = |g: &Game| {
+            let legal = Move::ALL
+                .into_iter()
+                .filter(|&mv| g.clone().apply(mv).is_ok());
+            if ai {
+                legal
+                    .map(|mv| (mc.evaluate(g, mv).unwrap_or(0.0), mv))
+                    .max_by(|a, b| a.0.total_cmp(&b.0))
+                    .map(|(_, mv)| mv)
+            } else {
+                legal.choose(&mut thread_rng())
+            }
         };
-        match res {
-            Err(game::Error::IllegalMove) => {
-                return 0.0;
+        for c in gen.play(seed, policy) {
+            if found == count {
+                break;
             }
-            Err(game::Error::GameOver) => {
-                avg += 1.0;
-                continue;
+            println!("{}", serde_json::to_string(&c)?);
+            if let Some(dir) = &out {
+                let path = format!("{}/puzzle-{}-d{}.json", dir, found, c.difficulty);
+                fs::write(path, serde_json::to_string_pretty(&c.puzzle)?)?;
             }
-            _ => {}
-        }
-        let c = run(&mut g);
-        if i % 100 == 50 {
-            stdout().execute(cursor::MoveTo(15, 12)).ok();
-            write!(stdout(), "score={:5.1}...", (avg / i as f32) + worst as f32).ok();
-        }
-        avg += c as f32;
-        if c < worst {
-            worst = c;
+            found += 1;
         }
+        seed += 1;
     }
-    (avg / (ITERS as f32)) + worst as f32
+    Ok(())
 }
 
-fn run(g: &mut Game) -> i32 {
-    let mut c = 0;
-    loop {
-        let mv = thread_rng().gen_range(0..4);
-        let res = match mv {
-            0 => g.up(),
-            1 => g.down(),
-            2 => g.left(),
-            _ => g.right(),
-        };
-        if res == Err(game::Error::GameOver) {
-            return c;
-        }
-        if res.is_ok() {
-            c += 1;
-        }
+// Usage: threes-ai-mc verify <result code> <replay.json>
+//
+// Checks a daily challenge result code against the replay it claims.
+fn verify(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let (Some(code), Some(path), None) = (args.next(), args.next(), args.next()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "usage: threes-ai-mc verify <result code> <replay.json>",
+        ));
+    };
+    let code: ResultCode = code
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let replay: Replay = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if code.verify(&replay) {
+        println!("valid: {} scored {} on {}", code, code.score, code.date);
+    } else {
+        println!("invalid: the replay does not match {}", code);
     }
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
-    let mut g = Game::new(192, 12);
+    let mut args = env::args().skip(1);
+    if let Some(cmd) = args.next() {
+        return match cmd.as_str() {
+            "analyze" => analyze(args),
+            "assist" => assist::assist(args),
+            "generate" => generate(args),
+            "verify" => verify(args),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown command {}", cmd),
+            )),
+        };
+    }
+
+    let mut g = Game::new(Tile::three_times_pow2(6), 12);
     crossterm::terminal::enable_raw_mode()?;
     stdout().execute(crossterm::cursor::Hide)?;
 
@@ -182,28 +267,28 @@ fn main() -> io::Result<()> {
         }
         mv = None;
         best = 0.0;
-        let sc = score(g.clone(), Move::Up);
+        let sc = score(&g, Move::Up);
         stdout().execute(cursor::MoveTo(1, 12))?;
         write!(stdout(), "UP: {}", sc)?;
         if sc > best {
             best = sc;
             mv = Some(Move::Up);
         }
-        let sc = score(g.clone(), Move::Down) * 1.05;
+        let sc = score(&g, Move::Down) * 1.05;
         stdout().execute(cursor::MoveTo(1, 13))?;
         write!(stdout(), "DOWN: {}", sc)?;
         if sc > best {
             best = sc;
             mv = Some(Move::Down);
         }
-        let sc = score(g.clone(), Move::Left) * 1.05;
+        let sc = score(&g, Move::Left) * 1.05;
         stdout().execute(cursor::MoveTo(1, 14))?;
         write!(stdout(), "LEFT: {}", sc)?;
         if sc > best {
             best = sc;
             mv = Some(Move::Left);
         }
-        let sc = score(g.clone(), Move::Right);
+        let sc = score(&g, Move::Right);
         stdout().execute(cursor::MoveTo(1, 15))?;
         write!(stdout(), "RIGHT: {}", sc)?;
         stdout().execute(cursor::MoveTo(1, 16))?;
@@ -211,13 +296,9 @@ fn main() -> io::Result<()> {
             best = sc;
             mv = Some(Move::Right);
         }
-        match mv {
-            Some(Move::Up) => g.up().ok(),
-            Some(Move::Down) => g.down().ok(),
-            Some(Move::Left) => g.left().ok(),
-            Some(Move::Right) => g.right().ok(),
-            None => continue,
-        };
+        if let Some(mv) = mv {
+            let _ = g.apply(mv);
+        }
     }
     stdout().execute(crossterm::style::ResetColor)?;
     stdout().execute(crossterm::cursor::Show)?;